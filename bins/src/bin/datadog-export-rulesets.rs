@@ -1,11 +1,19 @@
 use cli::datadog_utils::get_ruleset;
+use cli::rule_utils::{
+    get_ruleset_from_directory, get_rulesets_directories_from_directory, get_rulesets_from_file,
+    write_ruleset_to_directory,
+};
 use kernel::constants::VERSION;
 use kernel::model::ruleset::RuleSet;
+use kernel::signature_utils::{
+    encode_key, generate_signing_key, read_signing_key, sign_ruleset, write_signing_key,
+};
 
 use getopts::Options;
 use std::env;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::process::exit;
 
 fn print_usage(program: &str, opts: Options) {
@@ -18,7 +26,37 @@ fn main() {
     let program = args[0].clone();
     let mut opts = Options::new();
     opts.optopt("o", "output", "output file", "rulesets.json");
+    opts.optopt(
+        "",
+        "output-directory",
+        "directory where to write the rulesets, one ruleset directory per ruleset",
+        "rulesets",
+    );
     opts.optmulti("r", "ruleset", "ruleset to fetch", "python-security");
+    opts.optmulti(
+        "f",
+        "file",
+        "file with the rulesets to convert",
+        "rulesets.json",
+    );
+    opts.optmulti(
+        "d",
+        "directory",
+        "ruleset directory (or directory of ruleset directories) to convert",
+        "rulesets",
+    );
+    opts.optopt(
+        "",
+        "sign-key",
+        "sign the rulesets with the private key in this file",
+        "/path/to/private-key",
+    );
+    opts.optopt(
+        "",
+        "generate-key",
+        "generate a private key in this file and print its public key",
+        "/path/to/private-key",
+    );
     opts.optflag("h", "help", "print this help");
     opts.optflag("v", "version", "shows the version");
 
@@ -39,34 +77,77 @@ fn main() {
         exit(1);
     }
 
-    if !matches.opt_present("o") {
-        eprintln!("--output not defined");
+    if let Some(key_file) = matches.opt_str("generate-key") {
+        let key = generate_signing_key();
+        if let Err(e) = write_signing_key(Path::new(&key_file), &key) {
+            eprintln!("error when writing the key: {:#}", e);
+            exit(1);
+        }
+        println!("private key saved in file {}", key_file);
+        println!("public key: {}", encode_key(key.verifying_key().as_bytes()));
+        exit(0);
+    }
+
+    if !matches.opt_present("o") && !matches.opt_present("output-directory") {
+        eprintln!("--output or --output-directory not defined");
         print_usage(&program, opts);
         exit(1);
     }
 
-    if !matches.opt_present("r") {
-        eprintln!("--ruleset not defined");
+    if !matches.opt_present("r") && !matches.opt_present("f") && !matches.opt_present("d") {
+        eprintln!("--ruleset, --file or --directory not defined");
         print_usage(&program, opts);
         exit(1);
     }
 
-    let rulesets_names = matches.opt_strs("r");
-    let file_to_write = matches.opt_str("o").expect("output file");
-
-    // get the rulesets from the API
-    let rulesets: Vec<RuleSet> = rulesets_names
+    // get the rulesets from the API, the files and the ruleset directories
+    let mut rulesets: Vec<RuleSet> = matches
+        .opt_strs("r")
         .iter()
         .map(|ruleset_name| get_ruleset(ruleset_name).expect("error when reading ruleset"))
         .collect();
+    for file in matches.opt_strs("f") {
+        rulesets.extend(get_rulesets_from_file(file.as_str()).expect("error when reading file"));
+    }
+    for directory in matches.opt_strs("d") {
+        for ruleset_directory in get_rulesets_directories_from_directory(directory.as_str())
+            .expect("error when reading directory")
+        {
+            rulesets.push(
+                get_ruleset_from_directory(ruleset_directory.as_str())
+                    .expect("error when reading ruleset directory"),
+            );
+        }
+    }
+    if let Some(key_file) = matches.opt_str("sign-key") {
+        let key = read_signing_key(Path::new(&key_file)).expect("error when reading the key");
+        for ruleset in rulesets.iter_mut() {
+            sign_ruleset(ruleset, &key).expect("error when signing the ruleset");
+        }
+    }
+    let rulesets_names: Vec<String> = rulesets.iter().map(|r| r.name.clone()).collect();
 
-    let file = File::create(&file_to_write).expect("error when opening the output file");
-    let mut writer = BufWriter::new(file);
-    serde_json::to_writer(&mut writer, &rulesets).expect("error when writing the file");
-    writer.flush().expect("error when writing the file");
-    println!(
-        "rulesets {} saved in file {}",
-        rulesets_names.join(","),
-        file_to_write
-    );
+    if let Some(file_to_write) = matches.opt_str("o") {
+        let file = File::create(&file_to_write).expect("error when opening the output file");
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &rulesets).expect("error when writing the file");
+        writer.flush().expect("error when writing the file");
+        println!(
+            "rulesets {} saved in file {}",
+            rulesets_names.join(","),
+            file_to_write
+        );
+    }
+
+    if let Some(directory) = matches.opt_str("output-directory") {
+        for ruleset in &rulesets {
+            write_ruleset_to_directory(ruleset, &Path::new(&directory).join(&ruleset.name))
+                .expect("error when writing the ruleset directory");
+        }
+        println!(
+            "rulesets {} saved in directory {}",
+            rulesets_names.join(","),
+            directory
+        );
+    }
 }
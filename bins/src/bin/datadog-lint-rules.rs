@@ -0,0 +1,129 @@
+use cli::datadog_utils::get_ruleset;
+use cli::rule_lint_utils::{lint_ruleset, LintLevel};
+use cli::rule_utils::{
+    get_ruleset_from_directory, get_rulesets_directories_from_directory,
+    get_rulesets_files_from_directory, get_rulesets_from_file,
+};
+use kernel::analysis::dynamic_grammars::load_dynamic_grammars;
+use kernel::model::ruleset::RuleSet;
+
+use anyhow::Result;
+use getopts::Options;
+use std::env;
+use std::path::Path;
+use std::process::exit;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+// get the rulesets of a directory: the ruleset directories and the rulesets files
+fn get_rulesets_from_directory(directory: &str) -> Result<Vec<RuleSet>> {
+    let mut rulesets = vec![];
+    for ruleset_directory in get_rulesets_directories_from_directory(directory)? {
+        rulesets.push(get_ruleset_from_directory(ruleset_directory.as_str())?);
+    }
+    for file in get_rulesets_files_from_directory(directory)? {
+        rulesets.extend(get_rulesets_from_file(file.as_str())?);
+    }
+    Ok(rulesets)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = args[0].clone();
+    let mut opts = Options::new();
+    opts.optmulti("r", "ruleset", "ruleset to lint", "python-security");
+    opts.optmulti(
+        "f",
+        "file",
+        "file with the rulesets to lint",
+        "rulesets.json",
+    );
+    opts.optmulti(
+        "d",
+        "directory",
+        "directory with the rulesets to lint (ruleset directories or rulesets files)",
+        "rulesets",
+    );
+    opts.optopt(
+        "",
+        "grammars-directory",
+        "directory with additional tree-sitter grammars",
+        "/path/to/grammars",
+    );
+    opts.optflag("w", "warnings-as-errors", "fail when there are warnings");
+    opts.optflag("h", "help", "print this help");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(f) => {
+            panic!("error when parsing arguments: {}", f)
+        }
+    };
+
+    if matches.opt_present("h") {
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    if !matches.opt_present("r") && !matches.opt_present("f") && !matches.opt_present("d") {
+        eprintln!("no ruleset to lint, use --ruleset, --file or --directory");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    // grammars must be loaded before reading the rulesets that use them
+    if let Some(grammars_directory) = matches.opt_str("grammars-directory") {
+        if let Err(e) = load_dynamic_grammars(Path::new(&grammars_directory)) {
+            eprintln!("error when loading the grammars: {:?}", e);
+            exit(1);
+        }
+    }
+
+    let mut rulesets: Vec<RuleSet> = vec![];
+    let mut num_errors = 0;
+    let loaded_rulesets = matches
+        .opt_strs("r")
+        .iter()
+        .map(|r| get_ruleset(r.as_str()).map(|r| vec![r]))
+        .chain(
+            matches
+                .opt_strs("f")
+                .iter()
+                .map(|f| get_rulesets_from_file(f)),
+        )
+        .chain(
+            matches
+                .opt_strs("d")
+                .iter()
+                .map(|d| get_rulesets_from_directory(d)),
+        );
+    for loaded in loaded_rulesets {
+        match loaded {
+            Ok(r) => rulesets.extend(r),
+            Err(e) => {
+                eprintln!("error when reading the rulesets: {}", e);
+                num_errors += 1;
+            }
+        }
+    }
+
+    let mut num_warnings = 0;
+    for ruleset in &rulesets {
+        println!("Linting ruleset {}", ruleset.name);
+        for message in lint_ruleset(ruleset) {
+            match message.level {
+                LintLevel::Error => num_errors += 1,
+                LintLevel::Warning => num_warnings += 1,
+            }
+            println!("   {}", message);
+        }
+    }
+    println!("{} error(s), {} warning(s)", num_errors, num_warnings);
+
+    if num_errors > 0 || (matches.opt_present("w") && num_warnings > 0) {
+        exit(1);
+    }
+}
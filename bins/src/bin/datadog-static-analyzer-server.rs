@@ -1,5 +1,8 @@
 use getopts::Options;
-use kernel::constants::{CARGO_VERSION, VERSION};
+use kernel::analysis::dynamic_grammars::{get_dynamic_grammars, load_dynamic_grammars};
+use kernel::constants::{CARGO_VERSION, TREE_SITTER_GRAMMARS, VERSION};
+use kernel::model::common::Language;
+use kernel::signature_utils::{read_trusted_keys, VerifyingKey};
 use lazy_static::lazy_static;
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::fs::NamedFile;
@@ -8,7 +11,7 @@ use rocket::serde::json::{json, Json, Value};
 use rocket::{Request as RocketRequest, Response, State};
 use server::model::analysis_request::AnalysisRequest;
 use server::model::tree_sitter_tree_request::TreeSitterRequest;
-use server::request::process_analysis_request;
+use server::request::{process_analysis_request, process_signed_analysis_request};
 use server::tree_sitter_tree::process_tree_sitter_tree_request;
 use std::path::Path;
 use std::process::exit;
@@ -53,14 +56,29 @@ impl Fairing for CORS {
 fn languages() -> Value {
     let languages: Vec<Value> = kernel::model::common::ALL_LANGUAGES
         .iter()
+        .copied()
+        .chain(
+            get_dynamic_grammars()
+                .iter()
+                .map(|g| Language::Dynamic(g.name)),
+        )
         .map(|x| json!(x))
         .collect();
     json!(languages)
 }
 
 #[rocket::post("/analyze", format = "application/json", data = "<request>")]
-fn analyze(request: Json<AnalysisRequest>) -> Value {
-    json!(process_analysis_request(request.into_inner()))
+fn analyze(
+    server_configuration: &State<ServerConfiguration>,
+    request: Json<AnalysisRequest>,
+) -> Value {
+    match &server_configuration.trusted_keys {
+        Some(trusted_keys) => json!(process_signed_analysis_request(
+            request.into_inner(),
+            trusted_keys
+        )),
+        None => json!(process_analysis_request(request.into_inner())),
+    }
 }
 
 #[rocket::post("/get-treesitter-ast", format = "application/json", data = "<request>")]
@@ -73,6 +91,20 @@ fn get_version() -> String {
     CARGO_VERSION.to_string()
 }
 
+// JSON variant of /version, with the revision of every tree-sitter grammar
+#[rocket::get("/version", format = "application/json")]
+fn get_version_json() -> Value {
+    let grammars: Vec<Value> = TREE_SITTER_GRAMMARS
+        .iter()
+        .map(|(name, revision)| json!({"name": name, "revision": revision}))
+        .collect();
+    json!({
+        "version": CARGO_VERSION,
+        "revision": VERSION,
+        "grammars": grammars,
+    })
+}
+
 #[rocket::get("/revision", format = "text/plain")]
 fn get_revision() -> String {
     VERSION.to_string()
@@ -105,6 +137,8 @@ fn get_options() -> String {
 
 struct ServerConfiguration {
     static_directory: Option<String>,
+    // when defined, only the rules signed with one of these keys are executed
+    trusted_keys: Option<Vec<VerifyingKey>>,
 }
 
 struct ServerState {
@@ -152,6 +186,18 @@ fn rocket_main() -> _ {
         "how many seconds without a request the server will exit",
         "90",
     );
+    opts.optopt(
+        "",
+        "grammars-directory",
+        "directory with additional tree-sitter grammars to load (manifests and shared libraries)",
+        "/path/to/grammars",
+    );
+    opts.optopt(
+        "",
+        "trusted-keys",
+        "only execute rules signed with one of the public keys in this file",
+        "/path/to/trusted-keys",
+    );
     opts.optflag("h", "help", "print this help");
     opts.optflag("v", "version", "shows the tool version");
 
@@ -172,8 +218,23 @@ fn rocket_main() -> _ {
         exit(0);
     }
 
+    if let Some(grammars_directory) = matches.opt_str("grammars-directory") {
+        if let Err(e) = load_dynamic_grammars(Path::new(&grammars_directory)) {
+            eprintln!("error when loading grammars: {:?}", e);
+            exit(1);
+        }
+    }
+
+    let trusted_keys = matches.opt_str("trusted-keys").map(|file| {
+        read_trusted_keys(Path::new(&file)).unwrap_or_else(|e| {
+            eprintln!("error when reading the trusted keys: {:?}", e);
+            exit(1);
+        })
+    });
+
     let server_configuration = ServerConfiguration {
         static_directory: matches.opt_str("s"),
+        trusted_keys,
     };
 
     let mut rocket_configuration = rocket::config::Config::default();
@@ -227,6 +288,7 @@ fn rocket_main() -> _ {
         .mount("/", rocket::routes![analyze])
         .mount("/", rocket::routes![get_tree])
         .mount("/", rocket::routes![get_version])
+        .mount("/", rocket::routes![get_version_json])
         .mount("/", rocket::routes![get_revision])
         .mount("/", rocket::routes![ping])
         .mount("/", rocket::routes![get_options])
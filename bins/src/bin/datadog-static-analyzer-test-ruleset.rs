@@ -1,55 +1,60 @@
 use cli::datadog_utils::get_ruleset;
-use kernel::analysis::analyze::analyze;
-use kernel::model::analysis::AnalysisOptions;
-use kernel::model::rule::Rule;
+use cli::rule_test_report::{generate_json_report, generate_junit_report, RuleTestResult};
+use cli::rule_test_utils::{is_rule_selected, run_rule_tests, RuleTestOptions};
+use cli::rule_utils::{
+    get_ruleset_from_directory, get_rulesets_directories_from_directory,
+    get_rulesets_files_from_directory, get_rulesets_from_file,
+    write_ruleset_snapshots_to_directory, write_rulesets_to_file,
+};
+use kernel::model::ruleset::RuleSet;
 
-use anyhow::{Error, Result};
 use getopts::Options;
-use kernel::model::rule_test::RuleTest;
-use kernel::utils::decode_base64_string;
-use std::env;
+use rayon::prelude::*;
+use std::path::Path;
 use std::process::exit;
+use std::{env, fs};
+
+// where the rulesets come from. Local files and directories are updated in place
+// when updating the snapshots.
+enum RulesetsLocation {
+    Api,
+    File(String),
+    Directory(String),
+}
+
+// rulesets to test and where they come from
+struct RulesetsSource {
+    location: RulesetsLocation,
+    rulesets: Vec<RuleSet>,
+    snapshots_updated: bool,
+}
 
 fn print_usage(program: &str, opts: Options) {
     let brief = format!("Usage: {} FILE [options]", program);
     print!("{}", opts.usage(&brief));
 }
 
-fn test_rule(rule: &Rule, test: &RuleTest) -> Result<String> {
-    let rule_internal = rule.to_rule_internal().unwrap();
-    let code = decode_base64_string(test.code_base64.to_string()).unwrap();
-    let analysis_options = AnalysisOptions {
-        log_output: true,
-        use_debug: true,
-    };
-    let rules = vec![rule_internal];
-    let analyze_result = analyze(
-        &rule.language,
-        rules,
-        test.filename.as_str(),
-        code.as_str(),
-        &analysis_options,
-    );
-
-    if analyze_result.is_empty() {
-        Err(Error::msg("no violation result"))
-    } else {
-        let first_results = analyze_result.get(0).unwrap();
-
-        if first_results.violations.len() != test.annotation_count as usize {
-            let error =
-                format!(
-                "error evaluating test {}, expected {} annotations, got {}, execution error: {}, output: {}, rule errors: {}",
-                test.filename,
-                test.annotation_count,
-                first_results.violations.len(),
-                first_results.execution_error.clone().unwrap_or("none".to_string()),
-                first_results.output.clone().unwrap_or("none".to_string()),
-                first_results.errors.join(",")
-            );
-            Err(Error::msg(error))
-        } else {
-            Ok("test pass".to_string())
+fn print_results(results: &[RuleTestResult]) {
+    let mut previous: Option<(&str, &str)> = None;
+    for result in results {
+        if previous.map_or(true, |(ruleset, _)| ruleset != result.ruleset) {
+            println!("Testing ruleset {}", result.ruleset);
+        }
+        if previous.map_or(true, |(_, rule)| rule != result.rule) {
+            println!("   rule {} ... ", result.rule);
+        }
+        previous = Some((result.ruleset.as_str(), result.rule.as_str()));
+        match &result.error {
+            None if result.snapshot_updated => {
+                println!("      test {} passed, snapshot updated", result.filename)
+            }
+            None => println!("      test {} passed", result.filename),
+            Some(error) => {
+                println!("      test {} FAILED", result.filename);
+                for line in error.lines() {
+                    println!("         {}", line);
+                }
+            }
         }
     }
 }
@@ -60,6 +65,43 @@ fn main() {
     let mut opts = Options::new();
 
     opts.optmulti("r", "ruleset", "rules to test", "python-security");
+    opts.optmulti(
+        "f",
+        "file",
+        "file with the rulesets to test (same format as datadog-export-rulesets)",
+        "rulesets.json",
+    );
+    opts.optmulti(
+        "d",
+        "directory",
+        "directory with the rulesets to test (ruleset directories or rulesets files)",
+        "rulesets",
+    );
+    opts.optmulti(
+        "",
+        "rule",
+        "only test the rules matching this glob pattern",
+        "python-security/*",
+    );
+    opts.optopt("c", "cpus", "set the number of CPU, use to parallelize (default is the number of cores on the platform)", "--cpus 5");
+    opts.optflag(
+        "",
+        "update-snapshots",
+        "rewrite the expected fixed code of the tests",
+    );
+    opts.optopt(
+        "o",
+        "output",
+        "file where to save the rulesets from the API with updated snapshots",
+        "rulesets.json",
+    );
+    opts.optopt("", "report", "file where to write the report", "report.xml");
+    opts.optopt(
+        "",
+        "report-format",
+        "format of the report (default is junit)",
+        "junit/json",
+    );
     opts.optflag("h", "help", "print this help");
 
     let matches = match opts.parse(&args[1..]) {
@@ -74,33 +116,159 @@ fn main() {
         exit(1);
     }
 
-    let rulesets = matches.opt_strs("r");
-    let mut num_failures = 0;
-    for ruleset in rulesets {
+    if !matches.opt_present("r") && !matches.opt_present("f") && !matches.opt_present("d") {
+        eprintln!("no ruleset to test, use --ruleset, --file or --directory");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    let options = RuleTestOptions {
+        update_snapshots: matches.opt_present("update-snapshots"),
+    };
+
+    if options.update_snapshots && matches.opt_present("r") && !matches.opt_present("o") {
+        eprintln!("--output is required to update the snapshots of rulesets from the API");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    let report_format = matches
+        .opt_str("report-format")
+        .unwrap_or("junit".to_string());
+    if report_format != "junit" && report_format != "json" {
+        eprintln!("invalid report format {}", report_format);
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    let num_cpus = matches
+        .opt_str("c")
+        .map(|x| x.parse::<usize>().unwrap())
+        .unwrap_or(num_cpus::get());
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_cpus)
+        .build_global()
+        .expect("error when creating the thread pool");
+
+    // get the rulesets from the API, the files and the directories. A ruleset that
+    // cannot be loaded counts as a failure.
+    let mut sources: Vec<RulesetsSource> = vec![];
+    let mut num_load_errors = 0;
+    for ruleset in matches.opt_strs("r") {
         match get_ruleset(ruleset.as_str()) {
-            Ok(r) => {
-                println!("Testing ruleset {}", r.name);
-                for rule in r.rules.clone() {
-                    println!("   rule {} ... ", rule.name);
-                    let c = rule.clone();
-                    for t in rule.tests {
-                        match test_rule(&c, &t) {
-                            Ok(_) => {
-                                println!("      test {} passed", t.filename);
-                            }
-                            Err(e) => {
-                                println!("      test {} FAILED ({})", t.filename, e);
-                                num_failures += 1;
-                            }
-                        }
-                    }
-                }
-            }
+            Ok(r) => sources.push(RulesetsSource {
+                location: RulesetsLocation::Api,
+                rulesets: vec![r],
+                snapshots_updated: false,
+            }),
             Err(e) => {
                 eprintln!("error when trying to fetch the ruleset: {}", e);
+                num_load_errors += 1;
+            }
+        }
+    }
+    let mut files = matches.opt_strs("f");
+    let mut ruleset_directories = vec![];
+    for directory in matches.opt_strs("d") {
+        match get_rulesets_directories_from_directory(directory.as_str())
+            .and_then(|d| get_rulesets_files_from_directory(directory.as_str()).map(|f| (d, f)))
+        {
+            Ok((directories, directory_files)) => {
+                ruleset_directories.extend(directories);
+                files.extend(directory_files);
+            }
+            Err(e) => {
+                eprintln!("error when reading the rulesets directory: {}", e);
+                num_load_errors += 1;
+            }
+        }
+    }
+    for directory in ruleset_directories {
+        match get_ruleset_from_directory(directory.as_str()) {
+            Ok(ruleset) => sources.push(RulesetsSource {
+                location: RulesetsLocation::Directory(directory),
+                rulesets: vec![ruleset],
+                snapshots_updated: false,
+            }),
+            Err(e) => {
+                eprintln!("error when reading the ruleset from {}: {}", directory, e);
+                num_load_errors += 1;
+            }
+        }
+    }
+    for file in files {
+        match get_rulesets_from_file(file.as_str()) {
+            Ok(rulesets) => sources.push(RulesetsSource {
+                location: RulesetsLocation::File(file),
+                rulesets,
+                snapshots_updated: false,
+            }),
+            Err(e) => {
+                eprintln!("error when reading the rulesets from {}: {}", file, e);
+                num_load_errors += 1;
+            }
+        }
+    }
+
+    // run the tests, in parallel for all the rules
+    let rule_filters = matches.opt_strs("rule");
+    let mut results: Vec<RuleTestResult> = vec![];
+    for source in sources.iter_mut() {
+        for ruleset in source.rulesets.iter_mut() {
+            let ruleset_name = ruleset.name.clone();
+            let ruleset_results: Vec<Vec<RuleTestResult>> = ruleset
+                .rules
+                .par_iter_mut()
+                .filter(|rule| is_rule_selected(rule.name.as_str(), &rule_filters))
+                .map(|rule| run_rule_tests(ruleset_name.as_str(), rule, &options))
+                .collect();
+            for result in ruleset_results.into_iter().flatten() {
+                source.snapshots_updated |= result.snapshot_updated;
+                results.push(result);
+            }
+        }
+    }
+    print_results(&results);
+    let num_failures = results.iter().filter(|r| !r.is_success()).count() + num_load_errors;
+
+    // save the updated snapshots: local files are rewritten in place, rulesets from the API
+    // are saved in the output file (same format as datadog-export-rulesets)
+    if options.update_snapshots {
+        let mut api_rulesets: Vec<RuleSet> = vec![];
+        for source in sources {
+            match source.location {
+                RulesetsLocation::File(file) if source.snapshots_updated => {
+                    write_rulesets_to_file(Path::new(&file), &source.rulesets)
+                        .expect("error when writing the rulesets file");
+                    println!("rulesets saved in file {}", file);
+                }
+                RulesetsLocation::Directory(directory) if source.snapshots_updated => {
+                    for ruleset in &source.rulesets {
+                        write_ruleset_snapshots_to_directory(ruleset, Path::new(&directory))
+                            .expect("error when writing the snapshots");
+                    }
+                    println!("snapshots saved in directory {}", directory);
+                }
+                RulesetsLocation::File(_) | RulesetsLocation::Directory(_) => {}
+                RulesetsLocation::Api => api_rulesets.extend(source.rulesets),
             }
         }
+        if let Some(output) = matches.opt_str("o") {
+            write_rulesets_to_file(Path::new(&output), &api_rulesets)
+                .expect("error when writing the output file");
+            println!("rulesets with updated snapshots saved in file {}", output);
+        }
+    }
+
+    if let Some(report_file) = matches.opt_str("report") {
+        let report = match report_format.as_str() {
+            "json" => generate_json_report(&results),
+            _ => generate_junit_report(&results),
+        }
+        .expect("error when generating the report");
+        fs::write(&report_file, report).expect("error when writing the report");
+        println!("report saved in file {}", report_file);
     }
 
-    exit(num_failures)
+    exit(if num_failures > 0 { 1 } else { 0 })
 }
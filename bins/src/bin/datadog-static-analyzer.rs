@@ -1,27 +1,47 @@
-use cli::config_file::read_config_file;
-use cli::datadog_utils::get_rules_from_rulesets;
-use cli::file_utils::{filter_files_for_language, get_files, read_files_from_gitignore};
-use cli::model::config_file::ConfigFile;
+use cli::baseline::{apply_baseline, create_baseline, read_baseline, write_baseline};
+use cli::config_file::{
+    format_effective_config, get_config_for_path, get_directory_configs, get_rulesets_config,
+    read_config_file_with_path,
+};
+use cli::datadog_utils::get_datadog_site;
+use cli::file_utils::{
+    filter_files_for_language, get_files, is_ignored_path, FileSelectionOptions,
+};
+use cli::git_utils::{filter_violations_on_changed_lines, get_git_diff};
+use cli::json::generate_json_results;
+use cli::language_detection::LanguageDetectionOptions;
+use cli::lockfile::{
+    check_pinned_ruleset, generate_lockfile, get_lockfile_differences, read_lockfile,
+    write_lockfile,
+};
+use cli::model::config_file::{ConfigFile, DirectoryConfig};
+use cli::rule_config::{apply_rules_config, apply_severity_overrides, get_rules_for_path};
 use cli::rule_utils::{get_languages_for_rules, get_rulesets_from_file};
+use cli::ruleset_cache::{format_age, get_default_cache_directory, RulesetCache, RulesetOrigin};
 use itertools::Itertools;
 use kernel::analysis::analyze::analyze;
-use kernel::constants::{CARGO_VERSION, VERSION};
+use kernel::analysis::dynamic_grammars::load_dynamic_grammars;
+use kernel::analysis::tree_sitter::{get_grammar_name, validate_query};
+use kernel::constants::{CARGO_VERSION, TREE_SITTER_GRAMMARS, VERSION};
 use kernel::model::analysis::{AnalysisOptions, ERROR_RULE_TIMEOUT};
 use kernel::model::common::OutputFormat;
 use kernel::model::rule::{Rule, RuleInternal, RuleResult};
+use kernel::model::ruleset::RuleSet;
+use kernel::signature_utils::{read_trusted_keys, verify_ruleset};
 
 use anyhow::{Context, Result};
-use cli::constants::DEFAULT_MAX_FILE_SIZE_KB;
+use cli::constants::{DEFAULT_CACHE_TTL_SEC, DEFAULT_MAX_FILE_SIZE_KB};
 use cli::csv;
 use cli::model::cli_configuration::CliConfiguration;
 use cli::sarif::sarif_utils::generate_sarif_report;
 use getopts::Options;
 use indicatif::ProgressBar;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::{env, fs};
 
 fn print_usage(program: &str, opts: Options) {
@@ -68,6 +88,18 @@ fn print_configuration(configuration: &CliConfiguration) {
     println!("use debug        : {}", configuration.use_debug);
     println!("rules languages  : {}", languages_string.join(","));
     println!("max file size    : {} kb", configuration.max_file_size_kb);
+    println!(
+        "header files     : {}",
+        configuration.language_detection.header_files_language
+    );
+    println!(
+        "language mappings: {}",
+        configuration.language_detection.language_mappings.len()
+    );
+    println!(
+        "config files     : {}",
+        configuration.config_files.join(",")
+    );
 }
 
 fn main() -> Result<()> {
@@ -78,6 +110,7 @@ fn main() -> Result<()> {
     let mut use_configuration_file = false;
     let mut ignore_gitignore = false;
     let mut max_file_size_kb = DEFAULT_MAX_FILE_SIZE_KB;
+    let mut language_detection = LanguageDetectionOptions::default();
 
     opts.optopt(
         "i",
@@ -101,6 +134,93 @@ fn main() -> Result<()> {
         "path to ignore - the value is a glob",
         "**/test*.py (multiple values possible)",
     );
+    opts.optopt(
+        "",
+        "grammars-directory",
+        "directory with additional tree-sitter grammars to load (manifests and shared libraries)",
+        "/path/to/grammars",
+    );
+    opts.optopt(
+        "",
+        "trusted-keys",
+        "only use rulesets signed with one of the public keys in this file",
+        "/path/to/trusted-keys",
+    );
+    opts.optflag(
+        "",
+        "locked",
+        "fail if the rules from the API differ from the lockfile",
+    );
+    opts.optflag(
+        "",
+        "update-lockfile",
+        "write the lockfile with the rules from the API and exit",
+    );
+    opts.optflag(
+        "",
+        "print-effective-config",
+        "print the configuration after resolving extends and the configuration files of subdirectories, then exit",
+    );
+    opts.optflag(
+        "",
+        "offline",
+        "only use the rulesets from the cache, never query the API",
+    );
+    opts.optopt(
+        "",
+        "cache-ttl",
+        "how long rulesets from the cache are used without querying the API (default: 3600)",
+        "seconds",
+    );
+    opts.optopt(
+        "",
+        "cache-directory",
+        "directory of the cache of the rulesets (default: ~/.cache/datadog-static-analyzer)",
+        "/path/to/cache",
+    );
+    opts.optflag(
+        "",
+        "git-files",
+        "only analyze the files tracked by git when the directory is a git repository",
+    );
+    opts.optflag(
+        "",
+        "include-untracked",
+        "with --git-files, also analyze the files not tracked by git that are not ignored",
+    );
+    opts.optopt(
+        "",
+        "diff-base",
+        "only analyze the files added or modified since this git revision",
+        "origin/main",
+    );
+    opts.optflag(
+        "",
+        "diff-working-tree",
+        "with --diff-base, also analyze the changes not committed yet",
+    );
+    opts.optflag(
+        "",
+        "changed-lines-only",
+        "with --diff-base, only report the violations on the lines added or modified",
+    );
+    opts.optflag(
+        "",
+        "keep-new-files",
+        "with --changed-lines-only, also report the violations of the new files",
+    );
+    opts.optopt(
+        "",
+        "create-baseline",
+        "write the violations found in a baseline file and exit",
+        "baseline.json",
+    );
+    opts.optopt(
+        "",
+        "baseline",
+        "only report the violations that are not in this baseline file",
+        "baseline.json",
+    );
     opts.optflag("h", "help", "print this help");
     opts.optflag("v", "version", "shows the tool version");
     opts.optflag(
@@ -128,6 +248,10 @@ fn main() -> Result<()> {
 
     if matches.opt_present("v") {
         println!("Version: {}, revision: {}", CARGO_VERSION, VERSION);
+        println!("Grammars:");
+        for (name, revision) in TREE_SITTER_GRAMMARS {
+            println!("  {}: {}", name, revision);
+        }
         exit(1);
     }
 
@@ -136,12 +260,40 @@ fn main() -> Result<()> {
         exit(1);
     }
 
-    if !matches.opt_present("o") {
+    if !matches.opt_present("o")
+        && !matches.opt_present("update-lockfile")
+        && !matches.opt_present("create-baseline")
+        && !matches.opt_present("print-effective-config")
+    {
         eprintln!("output file not specified");
         print_usage(&program, opts);
         exit(1);
     }
 
+    if matches.opt_present("include-untracked") && !matches.opt_present("git-files") {
+        eprintln!("--include-untracked requires --git-files");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    if matches.opt_present("diff-working-tree") && !matches.opt_present("diff-base") {
+        eprintln!("--diff-working-tree requires --diff-base");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    if matches.opt_present("changed-lines-only") && !matches.opt_present("diff-base") {
+        eprintln!("--changed-lines-only requires --diff-base");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
+    if matches.opt_present("keep-new-files") && !matches.opt_present("changed-lines-only") {
+        eprintln!("--keep-new-files requires --changed-lines-only");
+        print_usage(&program, opts);
+        exit(1);
+    }
+
     let should_verify_checksum = !matches.opt_present("b");
     let add_git_info = matches.opt_present("g");
     let enable_performance_statistics = matches.opt_present("x");
@@ -159,9 +311,6 @@ fn main() -> Result<()> {
         .opt_str("d")
         .map(|value| value == "yes")
         .get_or_insert(env::var_os("DD_SA_DEBUG").is_some());
-    let output_file = matches
-        .opt_str("o")
-        .context("output file must be specified")?;
 
     // Show the ignore paths
     let mut ignore_paths: Vec<String> = Vec::new();
@@ -184,13 +333,27 @@ fn main() -> Result<()> {
         exit(1)
     }
 
-    let configuration_file: Option<ConfigFile> =
-        read_config_file(directory_to_analyze.as_str()).unwrap();
-    let mut rules: Vec<Rule> = Vec::new();
+    // grammars must be loaded before reading the configuration and the rules
+    // since they may reference the languages of these grammars.
+    if let Some(grammars_directory) = matches.opt_str("grammars-directory") {
+        let dynamic_languages = load_dynamic_grammars(Path::new(&grammars_directory))
+            .context("error when loading grammars")?;
+        if use_debug {
+            println!(
+                "Loaded grammars: {}",
+                dynamic_languages.iter().map(|l| l.to_string()).join(",")
+            );
+        }
+    }
+
+    let configuration_file: Option<(PathBuf, ConfigFile)> =
+        read_config_file_with_path(directory_to_analyze.as_str()).unwrap();
+    let mut rulesets: Vec<RuleSet> = Vec::new();
+    let mut directory_configs: Vec<DirectoryConfig> = Vec::new();
 
     // if there is a configuration file, we load the rules from it. But it means
     // we cannot have the rule parameter given.
-    if let Some(conf) = configuration_file {
+    if let Some((_, conf)) = &configuration_file {
         use_configuration_file = true;
         ignore_gitignore = conf.ignore_gitignore.unwrap_or(false);
         if rules_file.is_some() {
@@ -198,18 +361,30 @@ fn main() -> Result<()> {
             exit(1);
         }
 
-        let rules_from_api = get_rules_from_rulesets(&conf.rulesets);
-        rules.extend(rules_from_api.context("error when reading rules from API")?);
-
         // copy the ignore paths from the configuration file
-        if let Some(v) = conf.ignore_paths {
-            ignore_paths.extend(v);
+        if let Some(v) = &conf.ignore_paths {
+            ignore_paths.extend(v.clone());
         }
 
         // Get the max file size from the configuration or default to the default constant.
-        max_file_size_kb = conf.max_file_size_kb.unwrap_or(DEFAULT_MAX_FILE_SIZE_KB)
+        max_file_size_kb = conf.max_file_size_kb.unwrap_or(DEFAULT_MAX_FILE_SIZE_KB);
+
+        // Get how to classify header files (C, C++ or both) and the custom mappings
+        // between files and languages.
+        language_detection = LanguageDetectionOptions {
+            header_files_language: conf.header_files_language.unwrap_or_default(),
+            language_mappings: conf.language_mappings.clone().unwrap_or_default(),
+        };
     } else {
         use_configuration_file = false;
+        if matches.opt_present("update-lockfile") {
+            eprintln!("the lockfile can only be updated when using a configuration file");
+            exit(1);
+        }
+        if matches.opt_present("print-effective-config") {
+            eprintln!("no configuration file found");
+            exit(1);
+        }
         // if there is no config file, we must read the rules from a file.
         // Otherwise, we exit.
         if rules_file.is_none() {
@@ -219,28 +394,190 @@ fn main() -> Result<()> {
         }
 
         let rulesets_from_file = get_rulesets_from_file(rules_file.clone().unwrap().as_str());
-        let rules_from_file: Vec<Rule> = rulesets_from_file
-            .context("cannot read ruleset")?
-            .iter()
-            .flat_map(|v| v.rules.clone())
-            .collect();
-        rules.extend(rules_from_file);
+        rulesets.extend(rulesets_from_file.context("cannot read ruleset")?);
     }
 
     // add ignore path from the options
     ignore_paths.extend(ignore_paths_from_options);
 
-    // ignore all directories that are in gitignore
-    if !ignore_gitignore {
-        let paths_from_gitignore = read_files_from_gitignore(directory_to_analyze.as_str());
-        ignore_paths.extend(paths_from_gitignore.expect("error when reading gitignore file"));
+    // ignore all the files ignored by git (.gitignore files and .git/info/exclude)
+    let file_selection_options = FileSelectionOptions {
+        use_gitignore: !ignore_gitignore,
+        use_git_index: matches.opt_present("git-files"),
+        include_untracked: matches.opt_present("include-untracked"),
+    };
+    let mut files_to_analyze = get_files(
+        directory_to_analyze.as_str(),
+        &ignore_paths,
+        &file_selection_options,
+    )
+    .expect("unable to get the list of files to analyze");
+
+    if let Some((config_path, conf)) = configuration_file {
+        // the configuration files of the subdirectories apply to their subdirectories
+        directory_configs = get_directory_configs(
+            directory_to_analyze.as_str(),
+            &config_path,
+            conf,
+            &files_to_analyze,
+        )?;
+        files_to_analyze.retain(|f| {
+            let relative_path = f
+                .strip_prefix(directory_path)
+                .ok()
+                .and_then(|p| p.to_str())
+                .unwrap_or("");
+            get_config_for_path(&directory_configs, relative_path)
+                .and_then(|c| c.config.ignore_paths.as_ref())
+                .is_none_or(|p| !is_ignored_path(relative_path, p))
+        });
+        // the mappings of the subdirectories are matched first
+        let mut language_mappings = vec![];
+        for directory_config in directory_configs.iter().rev() {
+            for mapping in directory_config.config.language_mappings.iter().flatten() {
+                if !language_mappings.contains(mapping) {
+                    language_mappings.push(mapping.clone());
+                }
+            }
+        }
+        language_detection.language_mappings = language_mappings;
+        // show the configuration of each directory with a configuration file
+        if matches.opt_present("print-effective-config") {
+            print!("{}", format_effective_config(&directory_configs)?);
+            exit(0);
+        }
+        let rulesets_config = get_rulesets_config(&directory_configs)?;
+
+        // rulesets are read from the cache when they did not expire or when the API
+        // cannot be queried.
+        let cache_ttl = match matches.opt_str("cache-ttl") {
+            _ if matches.opt_present("update-lockfile") => 0,
+            Some(ttl) => ttl.parse::<u64>().context("invalid cache TTL")?,
+            None => DEFAULT_CACHE_TTL_SEC,
+        };
+        let cache = RulesetCache {
+            directory: matches
+                .opt_str("cache-directory")
+                .map(PathBuf::from)
+                .unwrap_or_else(get_default_cache_directory),
+            ttl: Duration::from_secs(cache_ttl),
+            offline: matches.opt_present("offline"),
+        };
+        let site = get_datadog_site();
+        let mut rulesets_from_api: Vec<RuleSet> = vec![];
+        for ruleset_config in &rulesets_config {
+            let result = cache
+                .get_ruleset(
+                    site.as_str(),
+                    ruleset_config.name(),
+                    ruleset_config.version(),
+                )
+                .context("error when reading rules from API")?;
+            match result.origin {
+                RulesetOrigin::Api | RulesetOrigin::Revalidated => {}
+                RulesetOrigin::Cache => println!(
+                    "ruleset {} read from the cache (fetched {} ago)",
+                    ruleset_config.name(),
+                    format_age(&result.age)
+                ),
+                RulesetOrigin::Offline => match &result.error {
+                    Some(error) => eprintln!(
+                        "ruleset {} read from the cache, the API cannot be queried (fetched {} ago): {}",
+                        ruleset_config.name(),
+                        format_age(&result.age),
+                        error
+                    ),
+                    None => eprintln!(
+                        "ruleset {} read from the cache without checking for updates (fetched {} ago)",
+                        ruleset_config.name(),
+                        format_age(&result.age)
+                    ),
+                },
+            }
+            rulesets_from_api.push(result.ruleset);
+        }
+        for (ruleset_config, ruleset) in rulesets_config.iter().zip(rulesets_from_api.iter()) {
+            check_pinned_ruleset(ruleset_config, ruleset)?;
+        }
+
+        // update the lockfile with the rules we just fetched or check that they
+        // did not change since the lockfile was generated.
+        if matches.opt_present("update-lockfile") {
+            let lockfile = generate_lockfile(&rulesets_config, &rulesets_from_api);
+            let lockfile_path = write_lockfile(directory_to_analyze.as_str(), &lockfile)?;
+            println!("lockfile saved in file {}", lockfile_path.display());
+            exit(0);
+        }
+        match read_lockfile(directory_to_analyze.as_str())? {
+            Some(lockfile) => {
+                let differences = get_lockfile_differences(&lockfile, &rulesets_from_api);
+                let level = if matches.opt_present("locked") {
+                    "error"
+                } else {
+                    "warning"
+                };
+                for difference in &differences {
+                    eprintln!("{}: rules differ from the lockfile: {}", level, difference);
+                }
+                if !differences.is_empty() && matches.opt_present("locked") {
+                    eprintln!("update the lockfile with --update-lockfile");
+                    exit(1);
+                }
+            }
+            None if matches.opt_present("locked") => {
+                eprintln!("no lockfile found, generate it with --update-lockfile");
+                exit(1);
+            }
+            None => {}
+        }
+        rulesets.extend(rulesets_from_api);
+    }
+
+    // only analyze the files added or modified since the base revision
+    let git_diff = match matches.opt_str("diff-base") {
+        Some(base) => {
+            let diff = get_git_diff(
+                directory_to_analyze.as_str(),
+                base.as_str(),
+                matches.opt_present("diff-working-tree"),
+            )
+            .context("error when getting the changed files")?;
+            files_to_analyze.retain(|f| {
+                f.strip_prefix(directory_path)
+                    .ok()
+                    .and_then(|p| p.to_str())
+                    .is_some_and(|p| diff.changed_files.contains(p))
+            });
+            println!(
+                "{} files changed since {} (commit {})",
+                files_to_analyze.len(),
+                base,
+                diff.base_commit
+            );
+            Some(diff)
+        }
+        None => None,
+    };
+
+    // only use rulesets signed with a trusted key if trusted keys are specified
+    if let Some(trusted_keys_file) = matches.opt_str("trusted-keys") {
+        let trusted_keys = read_trusted_keys(Path::new(&trusted_keys_file))
+            .context("error when reading the trusted keys")?;
+        for ruleset in &rulesets {
+            verify_ruleset(ruleset, &trusted_keys)
+                .context("error when verifying the signature of the rulesets")?;
+        }
+    }
+    // remove the disabled rules and override their severity from the configuration
+    let mut rules: Vec<Rule> = rulesets.iter().flat_map(|v| v.rules.clone()).collect();
+    if use_configuration_file {
+        rules = apply_rules_config(rules, &directory_configs);
     }
+    // there is no output file when creating a baseline
+    let output_file = matches.opt_str("o").unwrap_or_default();
 
     let languages = get_languages_for_rules(&rules);
 
-    let files_to_analyze = get_files(directory_to_analyze.as_str(), &ignore_paths)
-        .expect("unable to get the list of files to analyze");
-
     // we try to get the amount of cpu from the system. if the user set an option to force
     // the value. it overrides the value.
     let num_cpus = matches
@@ -249,7 +586,7 @@ fn main() -> Result<()> {
         .unwrap_or(num_cpus::get());
 
     // build the configuration object that contains how the CLI should behave.
-    let configuration = CliConfiguration {
+    let mut configuration = CliConfiguration {
         use_debug,
         use_configuration_file,
         ignore_gitignore,
@@ -261,6 +598,8 @@ fn main() -> Result<()> {
         rules,
         output_file,
         max_file_size_kb,
+        language_detection,
+        config_files: directory_configs.iter().map(|c| c.path.clone()).collect(),
     };
 
     print_configuration(&configuration);
@@ -289,6 +628,34 @@ fn main() -> Result<()> {
         println!("Skipping checksum verification");
     }
 
+    // get the grammars each tree-sitter query is valid for (e.g. a TypeScript query with
+    // JSX is only valid for .tsx files). Rules whose query is not valid for any grammar
+    // of their language are skipped.
+    let mut query_grammars: HashMap<String, Vec<String>> = HashMap::new();
+    let mut invalid_rules: Vec<String> = vec![];
+    for r in &configuration.rules {
+        let rule_internal = r
+            .to_rule_internal()
+            .context("cannot convert to rule internal")?;
+        if let Some(tree_sitter_query) = &rule_internal.tree_sitter_query {
+            match validate_query(tree_sitter_query.as_str(), &r.language) {
+                Ok(grammars) => {
+                    query_grammars.insert(r.name.clone(), grammars);
+                }
+                Err(e) => {
+                    eprintln!(
+                        "warning: skipping rule {}, invalid tree-sitter query: {}",
+                        r.name, e
+                    );
+                    invalid_rules.push(r.name.clone());
+                }
+            }
+        }
+    }
+    configuration
+        .rules
+        .retain(|r| !invalid_rules.contains(&r.name));
+
     // we always keep one thread free and some room for the management threads that monitor
     // the rule execution.
     let ideal_threads = ((configuration.num_cpus as f32 - 1.0) * 0.90) as usize;
@@ -305,7 +672,12 @@ fn main() -> Result<()> {
         .as_secs();
 
     for language in &languages {
-        let files_for_language = filter_files_for_language(&files_to_analyze, language);
+        let files_for_language = filter_files_for_language(
+            &files_to_analyze,
+            language,
+            &directory_to_analyze,
+            &configuration.language_detection,
+        );
 
         println!(
             "Analyzing {} {:?} files",
@@ -345,16 +717,50 @@ fn main() -> Result<()> {
             .into_par_iter()
             .flat_map(|path| match fs::read_to_string(&path) {
                 Ok(file_content) => {
-                    let res = analyze(
+                    let relative_path = path
+                        .strip_prefix(directory_path)
+                        .unwrap()
+                        .to_str()
+                        .expect("path contains non-Unicode characters");
+                    // the rules and their options come from the configuration of the
+                    // closest directory with a configuration file
+                    let directory_config = get_config_for_path(&directory_configs, relative_path);
+                    let rules_for_path = match directory_config {
+                        Some(c) => {
+                            if use_debug {
+                                println!(
+                                    "file {} uses the configuration {}",
+                                    relative_path, c.path
+                                );
+                            }
+                            get_rules_for_path(
+                                &rules_for_language,
+                                &c.config.rulesets,
+                                relative_path,
+                            )
+                        }
+                        None => rules_for_language.clone(),
+                    };
+                    // only execute the rules whose query is valid for the grammar of the file
+                    let grammar = get_grammar_name(language, relative_path);
+                    let rules_for_path: Vec<RuleInternal> = rules_for_path
+                        .into_iter()
+                        .filter(|r| {
+                            query_grammars
+                                .get(&r.name)
+                                .is_none_or(|g| g.contains(&grammar))
+                        })
+                        .collect();
+                    let mut res = analyze(
                         language,
-                        rules_for_language.clone(),
-                        path.strip_prefix(directory_path)
-                            .unwrap()
-                            .to_str()
-                            .expect("path contains non-Unicode characters"),
+                        rules_for_path,
+                        relative_path,
                         &file_content,
                         &analysis_options,
                     );
+                    if let Some(c) = directory_config {
+                        apply_severity_overrides(&mut res, &c.config.rulesets);
+                    }
 
                     if let Some(pb) = &progress_bar {
                         pb.inc(1);
@@ -375,11 +781,83 @@ fn main() -> Result<()> {
         }
     }
 
+    // record the violations found in a baseline
+    if let Some(baseline_path) = matches.opt_str("create-baseline") {
+        let baseline = create_baseline(&all_rule_results);
+        write_baseline(baseline_path.as_str(), &baseline)?;
+        println!(
+            "Baseline with {} violations written to {}",
+            baseline.violations.len(),
+            baseline_path
+        );
+        exit(0);
+    }
+
+    // do not report the violations of the baseline. This is done before keeping the
+    // violations on the changed lines: the violations of the baseline on the other lines
+    // of the analyzed files are still reported, not fixed.
+    if let Some(baseline_path) = matches.opt_str("baseline") {
+        let baseline = read_baseline(baseline_path.as_str())?;
+        let analyzed_files: HashSet<String> = files_to_analyze
+            .iter()
+            .filter_map(|f| Some(f.strip_prefix(directory_path).ok()?.to_str()?.to_string()))
+            .collect();
+        let count_violations = |rule_results: &[RuleResult]| -> usize {
+            rule_results.iter().map(|r| r.violations.len()).sum()
+        };
+        let num_violations = count_violations(&all_rule_results);
+        let fixed = apply_baseline(&mut all_rule_results, &baseline, &analyzed_files);
+        println!(
+            "Baseline: {} violations not reported, {} violations fixed",
+            num_violations - count_violations(&all_rule_results),
+            fixed.len()
+        );
+        for violation in &fixed {
+            println!(
+                "fixed: {} in {} (line {})",
+                violation.rule, violation.filename, violation.line
+            );
+        }
+    }
+
+    // only keep the violations on the changed lines
+    if let (true, Some(diff)) = (matches.opt_present("changed-lines-only"), &git_diff) {
+        filter_violations_on_changed_lines(
+            &mut all_rule_results,
+            diff,
+            matches.opt_present("keep-new-files"),
+        );
+    }
+
     let end_timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
+    // the configuration file used for each file, reported in the SARIF, JSON and CSV reports
+    let config_files: HashMap<String, String> = files_to_analyze
+        .iter()
+        .filter_map(|f| {
+            let relative_path = f.strip_prefix(directory_path).ok()?.to_str()?;
+            get_config_for_path(&directory_configs, relative_path)
+                .map(|c| (relative_path.to_string(), c.path.clone()))
+        })
+        .collect();
+    if directory_configs.len() > 1 {
+        println!("Configuration files");
+        println!("-------------------");
+        for directory_config in &directory_configs {
+            println!(
+                "{}: {} files",
+                directory_config.path,
+                config_files
+                    .values()
+                    .filter(|p| **p == directory_config.path)
+                    .count()
+            );
+        }
+    }
+
     let nb_violations: u32 = all_rule_results
         .iter()
         .map(|x| x.violations.len() as u32)
@@ -435,16 +913,17 @@ fn main() -> Result<()> {
     }
 
     let value = match configuration.output_format {
-        OutputFormat::Csv => csv::generate_csv_results(&all_rule_results),
-        OutputFormat::Json => {
-            serde_json::to_string(&all_rule_results).expect("error when getting the JSON report")
-        }
+        OutputFormat::Csv => csv::generate_csv_results(&all_rule_results, &config_files),
+        OutputFormat::Json => generate_json_results(&all_rule_results, &config_files)
+            .expect("error when getting the JSON report"),
         OutputFormat::Sarif => match generate_sarif_report(
             &configuration.rules,
             &all_rule_results,
             &directory_to_analyze,
             add_git_info,
             configuration.use_debug,
+            &config_files,
+            git_diff.as_ref(),
         ) {
             Ok(report) => {
                 serde_json::to_string(&report).expect("error when getting the SARIF report")
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, Context, Result};
+use kernel::model::rule::RuleResult;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use crate::constants::BASELINE_VERSION;
+use crate::model::baseline::{Baseline, BaselineViolation};
+
+// Read a baseline file. Baselines of another version cannot be used: the fingerprints
+// of their violations would not match.
+pub fn read_baseline(path: &str) -> Result<Baseline> {
+    let contents = fs::read_to_string(path).context("error when reading the baseline")?;
+    let baseline: Baseline =
+        serde_json::from_str(&contents).context("error when parsing the baseline")?;
+    if baseline.version != BASELINE_VERSION {
+        return Err(anyhow!(
+            "baseline {} has version {} but version {} is expected, create it again with --create-baseline",
+            path,
+            baseline.version,
+            BASELINE_VERSION
+        ));
+    }
+    Ok(baseline)
+}
+
+// Write a baseline file.
+pub fn write_baseline(path: &str, baseline: &Baseline) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(baseline)?)
+        .context("error when writing the baseline")
+}
+
+// Create the baseline of the violations of the results, sorted by file and line. The
+// violations without fingerprint are not recorded.
+pub fn create_baseline(rule_results: &[RuleResult]) -> Baseline {
+    let mut violations: Vec<BaselineViolation> = rule_results
+        .iter()
+        .flat_map(|rule_result| {
+            rule_result.violations.iter().filter_map(|v| {
+                Some(BaselineViolation {
+                    filename: rule_result.filename.clone(),
+                    line: v.start.line,
+                    rule: rule_result.rule_name.clone(),
+                    fingerprint: v.fingerprint.clone()?,
+                })
+            })
+        })
+        .collect();
+    violations.sort();
+    Baseline {
+        version: BASELINE_VERSION,
+        violations,
+    }
+}
+
+// Remove the violations of the results that are in the baseline and return the violations
+// of the baseline that are not reported anymore (i.e. that were fixed). Violations are
+// matched by fingerprint, a violation of the baseline matches at most one violation.
+// Only the violations of the analyzed files (relative paths) can be fixed: the other
+// files (e.g. not changed with --diff-base) were not checked.
+pub fn apply_baseline(
+    rule_results: &mut [RuleResult],
+    baseline: &Baseline,
+    analyzed_files: &HashSet<String>,
+) -> Vec<BaselineViolation> {
+    let mut remaining: HashMap<&str, Vec<&BaselineViolation>> = HashMap::new();
+    for violation in &baseline.violations {
+        remaining
+            .entry(violation.fingerprint.as_str())
+            .or_default()
+            .push(violation);
+    }
+
+    for rule_result in rule_results.iter_mut() {
+        rule_result.violations.retain(|v| {
+            v.fingerprint
+                .as_deref()
+                .and_then(|f| remaining.get_mut(f))
+                .and_then(|v| v.pop())
+                .is_none()
+        });
+    }
+
+    let mut fixed: Vec<BaselineViolation> = remaining
+        .into_values()
+        .flatten()
+        .filter(|v| analyzed_files.contains(&v.filename))
+        .cloned()
+        .collect();
+    fixed.sort();
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_temp_dir;
+    use kernel::model::common::PositionBuilder;
+    use kernel::model::rule::{RuleCategory, RuleResultBuilder, RuleSeverity};
+    use kernel::model::violation::{Violation, ViolationBuilder};
+
+    // a violation on a line of a file, with its fingerprint
+    fn violation(line: u32, content: &str) -> Violation {
+        let mut violation = ViolationBuilder::default()
+            .start(
+                PositionBuilder::default()
+                    .line(line)
+                    .col(1)
+                    .build()
+                    .unwrap(),
+            )
+            .end(
+                PositionBuilder::default()
+                    .line(line)
+                    .col(10)
+                    .build()
+                    .unwrap(),
+            )
+            .message("violation".to_string())
+            .severity(RuleSeverity::Error)
+            .category(RuleCategory::Security)
+            .fixes(vec![])
+            .build()
+            .unwrap();
+        violation.fingerprint =
+            Some(violation.compute_fingerprint("python-security/os-system", "main.py", content));
+        violation
+    }
+
+    fn rule_result(filename: &str, violations: Vec<Violation>) -> RuleResult {
+        RuleResultBuilder::default()
+            .rule_name("python-security/os-system".to_string())
+            .filename(filename.to_string())
+            .violations(violations)
+            .errors(vec![])
+            .execution_error(None)
+            .output(None)
+            .execution_time_ms(0)
+            .build()
+            .unwrap()
+    }
+
+    // violations of the baseline are not reported even if lines are added before them,
+    // the violations of the baseline that are not reported are fixed.
+    #[test]
+    fn test_apply_baseline() {
+        // two violations of the rule on the same line have the same fingerprint
+        let content = "import os\n\nos.system(a)\n\nprint(1)\n\nos.system(b)\n";
+        let baseline = create_baseline(&[
+            rule_result(
+                "main.py",
+                vec![
+                    violation(3, content),
+                    violation(3, content),
+                    violation(7, content),
+                ],
+            ),
+            rule_result("other.py", vec![violation(7, content)]),
+        ]);
+        assert_eq!(
+            vec![3, 3, 7, 7],
+            baseline
+                .violations
+                .iter()
+                .map(|v| v.line)
+                .collect::<Vec<u32>>()
+        );
+
+        let temp_dir = create_temp_dir(&[]);
+        let baseline_path = temp_dir.path().join("baseline.json");
+        write_baseline(baseline_path.to_str().unwrap(), &baseline).unwrap();
+        let baseline = read_baseline(baseline_path.to_str().unwrap()).unwrap();
+
+        // os.system(b) is fixed, one more violation is on os.system(a) and os.system(c)
+        // is new
+        let content = "# header\nimport os\n\n\nos.system(a)\n\nprint(1)\n\nos.system(c)\n";
+        let mut rule_results = vec![rule_result(
+            "main.py",
+            vec![
+                violation(5, content),
+                violation(5, content),
+                violation(5, content),
+                violation(9, content),
+            ],
+        )];
+        // other.py is not analyzed, its violation is not fixed
+        let analyzed_files = HashSet::from(["main.py".to_string()]);
+        let fixed = apply_baseline(&mut rule_results, &baseline, &analyzed_files);
+        assert_eq!(
+            vec![5, 9],
+            rule_results[0]
+                .violations
+                .iter()
+                .map(|v| v.start.line)
+                .collect::<Vec<u32>>()
+        );
+        assert_eq!(
+            vec![("main.py", 7)],
+            fixed
+                .iter()
+                .map(|v| (v.filename.as_str(), v.line))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_read_baseline_version() {
+        let temp_dir = create_temp_dir(&[("baseline.json", r#"{"violations": []}"#)]);
+        let baseline_path = temp_dir.path().join("baseline.json");
+        let baseline_path = baseline_path.to_str().unwrap();
+
+        assert!(read_baseline(baseline_path)
+            .unwrap_err()
+            .to_string()
+            .contains("has version 0 but version 1 is expected"));
+
+        write_baseline(baseline_path, &create_baseline(&[])).unwrap();
+        assert_eq!(
+            BASELINE_VERSION,
+            read_baseline(baseline_path).unwrap().version
+        );
+    }
+}
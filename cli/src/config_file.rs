@@ -1,12 +1,21 @@
 use anyhow::{anyhow, Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use crate::config_file_schema::validate_config_file;
 use crate::constants;
 use crate::model;
+use crate::model::config_file::{
+    ConfigFile, DetailedRulesetConfig, DirectoryConfig, LanguageMapping, RuleConfig, RulesetConfig,
+};
 
+// The configuration is validated against its schema first so that the errors (e.g. a
+// misspelled key) are reported with their position in the file.
 fn parse_config_file(config_contents: &str) -> Result<model::config_file::ConfigFile> {
+    validate_config_file(config_contents)?;
     Ok(serde_yaml::from_str(config_contents)?)
 }
 
@@ -14,7 +23,8 @@ fn parse_config_file(config_contents: &str) -> Result<model::config_file::Config
 // If it fails, we try to read static-analysis.datadog.yaml
 // If the file does not exist, we return a Ok(None).
 // If there is an error reading the file, we return a failure
-pub fn read_config_file(path: &str) -> Result<Option<model::config_file::ConfigFile>> {
+// The path of the file that was read is returned with the configuration.
+pub fn read_config_file_with_path(path: &str) -> Result<Option<(PathBuf, ConfigFile)>> {
     let yml_file_path = Path::new(path).join(format!(
         "{}.yml",
         constants::DATADOG_CONFIG_FILE_WITHOUT_PREFIX
@@ -25,17 +35,17 @@ pub fn read_config_file(path: &str) -> Result<Option<model::config_file::ConfigF
     ));
 
     // first, static-analysis.datadog.yml
-    let mut file = match File::open(yml_file_path) {
-        Ok(f) => f,
+    let (mut file, file_path) = match File::open(&yml_file_path) {
+        Ok(f) => (f, yml_file_path),
         Err(e1) if e1.kind() == std::io::ErrorKind::NotFound => {
             // second, static-analysis.datadog.yaml
-            match File::open(yaml_file_path) {
-                Ok(f) => f,
+            match File::open(&yaml_file_path) {
+                Ok(f) => (f, yaml_file_path),
                 Err(e2) if e2.kind() == std::io::ErrorKind::NotFound => return Ok(None),
-                otherwise => otherwise?,
+                Err(e2) => return Err(e2.into()),
             }
         }
-        otherwise => otherwise?,
+        Err(e1) => return Err(e1.into()),
     };
     let mut contents = String::new();
 
@@ -46,12 +56,352 @@ pub fn read_config_file(path: &str) -> Result<Option<model::config_file::ConfigF
         return Err(anyhow!("the config file is empty"));
     }
 
-    Ok(Some(parse_config_file(&contents)?))
+    let config = parse_config_file(&contents)
+        .with_context(|| format!("error in configuration file {}", file_path.display()))?;
+    let config = resolve_extends(config, &file_path, &mut vec![])?;
+    Ok(Some((file_path, config)))
+}
+
+// Merge a configuration with the configuration files it extends, recursively. The files
+// are merged in the order of the list and the configuration itself is merged last (see
+// `merge_config_files`). The stack is the list of files being resolved, to detect cycles.
+fn resolve_extends(
+    mut config: ConfigFile,
+    path: &Path,
+    stack: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<ConfigFile> {
+    let Some(extends) = config.extends.take() else {
+        return Ok(config);
+    };
+    let canonical_path = path
+        .canonicalize()
+        .with_context(|| format!("cannot read configuration file {}", path.display()))?;
+    if stack.iter().any(|(p, _)| *p == canonical_path) {
+        let cycle: Vec<String> = stack
+            .iter()
+            .skip_while(|(p, _)| *p != canonical_path)
+            .map(|(_, p)| p.display().to_string())
+            .chain([path.display().to_string()])
+            .collect();
+        return Err(anyhow!(
+            "cycle in the extended configuration files: {}",
+            cycle.join(" -> ")
+        ));
+    }
+    stack.push((canonical_path, path.to_path_buf()));
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut base = ConfigFile::default();
+    for extended in extends {
+        let extended_path = directory.join(extended);
+        let contents = fs::read_to_string(&extended_path).with_context(|| {
+            format!(
+                "cannot read configuration file {} extended by {}",
+                extended_path.display(),
+                path.display()
+            )
+        })?;
+        let extended_config = parse_config_file(&contents)
+            .with_context(|| format!("error in configuration file {}", extended_path.display()))?;
+        let extended_config = resolve_extends(extended_config, &extended_path, stack)?;
+        base = merge_config_files(&base, &extended_config);
+    }
+
+    stack.pop();
+    Ok(merge_config_files(&base, &config))
+}
+
+pub fn read_config_file(path: &str) -> Result<Option<ConfigFile>> {
+    Ok(read_config_file_with_path(path)?.map(|(_, config)| config))
+}
+
+// Prefix a path or a glob with the directory of its configuration file.
+fn rebase_path(path: &str, directory: &str) -> String {
+    format!(
+        "{}/{}",
+        directory,
+        path.trim_start_matches("./").trim_start_matches('/')
+    )
+}
+
+fn rebase_paths(paths: Vec<String>, directory: &str) -> Vec<String> {
+    paths.iter().map(|p| rebase_path(p, directory)).collect()
+}
+
+fn rebase_rule_config(config: RuleConfig, directory: &str) -> RuleConfig {
+    RuleConfig {
+        only: config.only.map(|only| rebase_paths(only, directory)),
+        ignore: rebase_paths(config.ignore, directory),
+        ..config
+    }
+}
+
+/// Make the paths of a configuration file relative to the analyzed directory instead
+/// of the directory of the configuration file.
+pub fn rebase_config_file(config: ConfigFile, directory: &str) -> ConfigFile {
+    let rulesets = config
+        .rulesets
+        .into_iter()
+        .map(|ruleset_config| match ruleset_config {
+            RulesetConfig::Name(name) => RulesetConfig::Name(name),
+            RulesetConfig::Detailed(d) => RulesetConfig::Detailed(DetailedRulesetConfig {
+                config: rebase_rule_config(d.config, directory),
+                rules: d
+                    .rules
+                    .into_iter()
+                    .map(|(name, c)| (name, rebase_rule_config(c, directory)))
+                    .collect(),
+                ..d
+            }),
+        })
+        .collect();
+    ConfigFile {
+        rulesets,
+        ignore_paths: config.ignore_paths.map(|p| rebase_paths(p, directory)),
+        language_mappings: config.language_mappings.map(|mappings| {
+            mappings
+                .into_iter()
+                .map(|m| LanguageMapping {
+                    pattern: rebase_path(m.pattern.as_str(), directory),
+                    language: m.language,
+                })
+                .collect()
+        }),
+        ..config
+    }
+}
+
+// Concatenate two lists, without the duplicates.
+fn merge_lists<T: Clone + PartialEq>(first: &[T], second: &[T]) -> Vec<T> {
+    let mut result = first.to_vec();
+    for v in second {
+        if !result.contains(v) {
+            result.push(v.clone());
+        }
+    }
+    result
+}
+
+fn merge_optional_lists<T: Clone + PartialEq>(
+    first: &Option<Vec<T>>,
+    second: &Option<Vec<T>>,
+) -> Option<Vec<T>> {
+    match (first, second) {
+        (Some(f), Some(s)) => Some(merge_lists(f, s)),
+        (f, s) => f.clone().or_else(|| s.clone()),
+    }
+}
+
+// The options of the child configuration take precedence, except the ignored paths that
+// are ignored by both configurations.
+fn merge_rule_config(parent: &RuleConfig, child: &RuleConfig) -> RuleConfig {
+    RuleConfig {
+        enabled: child.enabled.or(parent.enabled),
+        severity: child.severity.or(parent.severity),
+        only: child.only.clone().or_else(|| parent.only.clone()),
+        ignore: merge_lists(&parent.ignore, &child.ignore),
+    }
+}
+
+fn merge_ruleset_config(parent: &RulesetConfig, child: &RulesetConfig) -> RulesetConfig {
+    match (parent, child) {
+        (_, RulesetConfig::Name(_)) => parent.clone(),
+        (RulesetConfig::Name(_), _) => child.clone(),
+        (RulesetConfig::Detailed(p), RulesetConfig::Detailed(c)) => {
+            let mut rules = p.rules.clone();
+            for (name, rule_config) in &c.rules {
+                let merged = match p.rules.get(name) {
+                    Some(parent_rule_config) => merge_rule_config(parent_rule_config, rule_config),
+                    None => rule_config.clone(),
+                };
+                rules.insert(name.clone(), merged);
+            }
+            RulesetConfig::Detailed(DetailedRulesetConfig {
+                name: c.name.clone(),
+                version: c.version.clone().or_else(|| p.version.clone()),
+                checksum: c.checksum.clone().or_else(|| p.checksum.clone()),
+                config: merge_rule_config(&p.config, &c.config),
+                rules,
+            })
+        }
+    }
+}
+
+/// Merge a configuration with the configuration of its parent. The rulesets and the
+/// ignored paths of both configurations are used and the options of the child take
+/// precedence. A ruleset in both configurations is merged option by option and rule
+/// by rule. The custom language mappings of the child are matched first.
+pub fn merge_config_files(parent: &ConfigFile, child: &ConfigFile) -> ConfigFile {
+    let mut rulesets = parent.rulesets.clone();
+    for ruleset_config in &child.rulesets {
+        match rulesets
+            .iter_mut()
+            .find(|r| r.name() == ruleset_config.name())
+        {
+            Some(r) => *r = merge_ruleset_config(r, ruleset_config),
+            None => rulesets.push(ruleset_config.clone()),
+        }
+    }
+    ConfigFile {
+        extends: None,
+        rulesets,
+        ignore_paths: merge_optional_lists(&parent.ignore_paths, &child.ignore_paths),
+        ignore_gitignore: child.ignore_gitignore.or(parent.ignore_gitignore),
+        max_file_size_kb: child.max_file_size_kb.or(parent.max_file_size_kb),
+        header_files_language: child.header_files_language.or(parent.header_files_language),
+        language_mappings: merge_optional_lists(
+            &child.language_mappings,
+            &parent.language_mappings,
+        ),
+    }
+}
+
+fn is_in_directory(relative_path: &str, directory: &str) -> bool {
+    directory.is_empty() || Path::new(relative_path).starts_with(directory)
+}
+
+/// Get the configuration that applies to a path relative to the analyzed directory:
+/// the configuration of the closest directory with a configuration file.
+/// The configurations must be sorted by directory (see `get_directory_configs`).
+pub fn get_config_for_path<'a>(
+    directory_configs: &'a [DirectoryConfig],
+    relative_path: &str,
+) -> Option<&'a DirectoryConfig> {
+    directory_configs
+        .iter()
+        .rev()
+        .find(|c| is_in_directory(relative_path, c.directory.as_str()))
+}
+
+/// Get the configuration of the analyzed directory and of its subdirectories with a
+/// configuration file, sorted by directory. Only the configuration files in the list of
+/// files to analyze are read so that the configuration files of the ignored directories
+/// are not used. The configuration of a subdirectory is merged with the configuration of
+/// its closest parent with a configuration file (see `merge_config_files`).
+pub fn get_directory_configs(
+    directory: &str,
+    config_path: &Path,
+    config: ConfigFile,
+    files: &[PathBuf],
+) -> Result<Vec<DirectoryConfig>> {
+    let mut directory_configs = vec![DirectoryConfig {
+        directory: "".to_string(),
+        path: get_relative_path(config_path, directory),
+        config,
+    }];
+
+    let config_filenames = [
+        format!("{}.yml", constants::DATADOG_CONFIG_FILE_WITHOUT_PREFIX),
+        format!("{}.yaml", constants::DATADOG_CONFIG_FILE_WITHOUT_PREFIX),
+    ];
+    let subdirectories: BTreeSet<String> = files
+        .iter()
+        .filter(|f| {
+            f.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| config_filenames.iter().any(|c| c == n))
+        })
+        .filter_map(|f| f.parent())
+        .map(|d| get_relative_path(d, directory))
+        .filter(|d| !d.is_empty())
+        .collect();
+
+    for subdirectory in subdirectories {
+        let Some((path, nested_config)) = read_config_file_with_path(
+            Path::new(directory)
+                .join(&subdirectory)
+                .to_str()
+                .unwrap_or(""),
+        )?
+        else {
+            continue;
+        };
+        let relative_path = get_relative_path(&path, directory);
+        for (option, is_set) in [
+            ("ignore-gitignore", nested_config.ignore_gitignore.is_some()),
+            ("max-file-size-kb", nested_config.max_file_size_kb.is_some()),
+            (
+                "header-files-language",
+                nested_config.header_files_language.is_some(),
+            ),
+        ] {
+            if is_set {
+                eprintln!(
+                    "warning: {} is only read from the configuration file of the analyzed directory, ignored in {}",
+                    option, relative_path
+                );
+            }
+        }
+        let parent = get_config_for_path(&directory_configs, subdirectory.as_str())
+            .map(|c| c.config.clone())
+            .unwrap_or_default();
+        directory_configs.push(DirectoryConfig {
+            config: merge_config_files(
+                &parent,
+                &rebase_config_file(nested_config, subdirectory.as_str()),
+            ),
+            directory: subdirectory,
+            path: relative_path,
+        });
+    }
+    Ok(directory_configs)
+}
+
+fn get_relative_path(path: &Path, directory: &str) -> String {
+    path.strip_prefix(directory)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Format the configuration of each directory with a configuration file as YAML, each
+/// configuration preceded by a comment with the path of its configuration file.
+pub fn format_effective_config(directory_configs: &[DirectoryConfig]) -> Result<String> {
+    let mut output = String::new();
+    for directory_config in directory_configs {
+        output.push_str(format!("# {}\n", directory_config.path).as_str());
+        output.push_str(serde_yaml::to_string(&directory_config.config)?.as_str());
+    }
+    Ok(output)
+}
+
+/// Get the rulesets used by all the configurations, to fetch each ruleset once. A ruleset
+/// cannot be pinned to different versions or checksums in different configurations.
+pub fn get_rulesets_config(directory_configs: &[DirectoryConfig]) -> Result<Vec<RulesetConfig>> {
+    let mut rulesets_config: Vec<(RulesetConfig, &str)> = vec![];
+    for directory_config in directory_configs {
+        for ruleset_config in &directory_config.config.rulesets {
+            match rulesets_config
+                .iter()
+                .find(|(r, _)| r.name() == ruleset_config.name())
+            {
+                Some((r, path))
+                    if r.version() != ruleset_config.version()
+                        || r.checksum() != ruleset_config.checksum() =>
+                {
+                    return Err(anyhow!(
+                        "ruleset {} is pinned differently in {} and {}",
+                        r.name(),
+                        path,
+                        directory_config.path
+                    ))
+                }
+                Some(_) => {}
+                None => {
+                    rulesets_config.push((ruleset_config.clone(), directory_config.path.as_str()))
+                }
+            }
+        }
+    }
+    Ok(rulesets_config.into_iter().map(|(r, _)| r).collect())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::config_file::HeaderFilesLanguage;
+    use kernel::model::common::Language;
+    use kernel::model::rule::RuleSeverity;
 
     // test when we have only rulesets. We should then have the ignore-paths set to None
     #[test]
@@ -84,6 +434,329 @@ ignore-paths:
         assert_eq!("path1", ignore_paths.get(1).unwrap().as_str());
     }
 
+    // the language used for header files is read from the configuration file
+    #[test]
+    fn parse_config_file_with_header_files_language() {
+        let data = r#"
+rulesets:
+  - c-security
+header-files-language: cpp
+    "#;
+        let res = parse_config_file(data);
+        assert!(res.is_ok());
+        assert_eq!(
+            Some(HeaderFilesLanguage::Cpp),
+            res.unwrap().header_files_language
+        );
+
+        let data = r#"
+rulesets:
+  - c-security
+header-files-language: fortran
+    "#;
+        assert!(parse_config_file(data).is_err());
+    }
+
+    // custom mappings between globs and languages
+    #[test]
+    fn parse_config_file_with_language_mappings() {
+        let data = r#"
+rulesets:
+  - javascript-best-practices
+language-mappings:
+  - pattern: "**/*.jsm"
+    language: JAVASCRIPT
+  - pattern: "legacy/**/*.h"
+    language: CPP
+    "#;
+        let res = parse_config_file(data);
+        assert!(res.is_ok());
+        let language_mappings = res.unwrap().language_mappings.unwrap();
+        assert_eq!(2, language_mappings.len());
+        assert_eq!(
+            &LanguageMapping {
+                pattern: "**/*.jsm".to_string(),
+                language: Language::JavaScript
+            },
+            language_mappings.get(0).unwrap()
+        );
+        assert_eq!(Language::Cpp, language_mappings.get(1).unwrap().language);
+    }
+
+    // rulesets can be pinned to a version and/or a checksum
+    #[test]
+    fn parse_config_file_with_pinned_rulesets() {
+        let data = r#"
+rulesets:
+  - python-best-practices
+  - name: python-security
+    version: "12"
+    checksum: 5d41402abc4b2a76b9719d911017c592
+    "#;
+        let rulesets = parse_config_file(data).unwrap().rulesets;
+        assert_eq!(
+            vec![
+                RulesetConfig::Name("python-best-practices".to_string()),
+                RulesetConfig::Detailed(DetailedRulesetConfig {
+                    name: "python-security".to_string(),
+                    version: Some("12".to_string()),
+                    checksum: Some("5d41402abc4b2a76b9719d911017c592".to_string()),
+                    ..Default::default()
+                })
+            ],
+            rulesets
+        );
+        assert_eq!("python-security", rulesets[1].name());
+        assert_eq!(Some("12"), rulesets[1].version());
+        assert_eq!(None, rulesets[0].checksum());
+    }
+
+    // configuration of the rules of a ruleset and of a specific rule
+    #[test]
+    fn parse_config_file_with_rules_config() {
+        let data = r#"
+rulesets:
+  - name: python-security
+    only:
+      - src/**
+    rules:
+      subprocess-shell-true:
+        ignore:
+          - scripts/**
+        severity: NOTICE
+      no-eval:
+        enabled: false
+    "#;
+        let rulesets = parse_config_file(data).unwrap().rulesets;
+        assert_eq!(
+            Some(&RuleConfig {
+                only: Some(vec!["src/**".to_string()]),
+                ..Default::default()
+            }),
+            rulesets[0].config()
+        );
+        assert_eq!(
+            Some(&RuleConfig {
+                ignore: vec!["scripts/**".to_string()],
+                severity: Some(RuleSeverity::Notice),
+                ..Default::default()
+            }),
+            rulesets[0].rule_config("subprocess-shell-true")
+        );
+        assert_eq!(
+            Some(false),
+            rulesets[0].rule_config("no-eval").unwrap().enabled
+        );
+        assert!(rulesets[0].rule_config("other").is_none());
+    }
+
+    // unknown keys are reported with their position and the key that was likely meant
+    #[test]
+    fn parse_config_file_with_unknown_keys() {
+        let data = r#"
+rulesets:
+  - name: python-security
+    rules:
+      no-eval:
+        enable: false
+ignore_paths:
+  - "**/test/**"
+    "#;
+        let error = parse_config_file(data).unwrap_err().to_string();
+        assert!(error.contains("line 6, column 9: unknown key enable (did you mean enabled?)"));
+        assert!(error
+            .contains("line 7, column 1: unknown key ignore_paths (did you mean ignore-paths?)"));
+    }
+
+    // values that do not match the schema are reported with their position
+    #[test]
+    fn parse_config_file_with_invalid_values() {
+        let data = r#"
+rulesets:
+  - python-security
+  - name: python-best-practices
+    severity: CRITICAL
+max-file-size-kb: big
+    "#;
+        let error = parse_config_file(data).unwrap_err().to_string();
+        assert!(error.contains("line 5, column 5: "));
+        assert!(error.contains("line 6, column 1: "));
+
+        let data = r#"
+rulesets:
+  - version: "3"
+    "#;
+        let error = parse_config_file(data).unwrap_err().to_string();
+        assert!(error.contains("line 3, column 5: "));
+    }
+
+    // the options of the child take precedence and a ruleset in both configurations
+    // is merged rule by rule
+    #[test]
+    fn test_merge_config_files() {
+        let parent = parse_config_file(
+            r#"
+rulesets:
+  - python-best-practices
+  - name: python-security
+    version: "3"
+    rules:
+      no-eval:
+        severity: NOTICE
+        ignore:
+          - scripts/**
+ignore-paths:
+  - tests
+max-file-size-kb: 100
+"#,
+        )
+        .unwrap();
+        let child = parse_config_file(
+            r#"
+rulesets:
+  - python-inclusive
+  - name: python-security
+    rules:
+      no-eval:
+        enabled: false
+        ignore:
+          - tools/**
+ignore-paths:
+  - generated
+max-file-size-kb: 200
+"#,
+        )
+        .unwrap();
+        let merged = merge_config_files(&parent, &rebase_config_file(child, "services/a"));
+        assert_eq!(
+            vec![
+                "python-best-practices",
+                "python-security",
+                "python-inclusive"
+            ],
+            merged.rulesets.iter().map(|r| r.name()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("3"), merged.rulesets[1].version());
+        assert_eq!(
+            Some(&RuleConfig {
+                enabled: Some(false),
+                severity: Some(RuleSeverity::Notice),
+                only: None,
+                ignore: vec!["scripts/**".to_string(), "services/a/tools/**".to_string()],
+            }),
+            merged.rulesets[1].rule_config("no-eval")
+        );
+        assert_eq!(
+            Some(vec![
+                "tests".to_string(),
+                "services/a/generated".to_string()
+            ]),
+            merged.ignore_paths
+        );
+        assert_eq!(Some(200), merged.max_file_size_kb);
+    }
+
+    // the configuration files of the subdirectories apply to their subdirectories
+    #[test]
+    fn test_get_directory_configs() {
+        let directory =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test/config_file/nested");
+        let nested_directory = directory.join("services").join("a");
+        let nested_config_path = nested_directory.join("static-analysis.datadog.yaml");
+
+        let directory_str = directory.to_str().unwrap();
+        let (config_path, config) = read_config_file_with_path(directory_str).unwrap().unwrap();
+        let directory_configs = get_directory_configs(
+            directory_str,
+            &config_path,
+            config,
+            &[nested_config_path, nested_directory.join("main.py")],
+        )
+        .unwrap();
+        assert_eq!(2, directory_configs.len());
+        assert_eq!(
+            "services/a/static-analysis.datadog.yaml",
+            directory_configs[1].path
+        );
+        assert_eq!(2, directory_configs[1].config.rulesets.len());
+
+        let config_path = |path: &str| {
+            get_config_for_path(&directory_configs, path)
+                .unwrap()
+                .path
+                .as_str()
+        };
+        assert_eq!("static-analysis.datadog.yml", config_path("main.py"));
+        assert_eq!(
+            "static-analysis.datadog.yml",
+            config_path("services/ab/main.py")
+        );
+        assert_eq!(
+            "services/a/static-analysis.datadog.yaml",
+            config_path("services/a/src/main.py")
+        );
+        assert_eq!(
+            vec!["python-security", "java-security"],
+            get_rulesets_config(&directory_configs)
+                .unwrap()
+                .iter()
+                .map(|r| r.name())
+                .collect::<Vec<_>>()
+        );
+
+        // a ruleset cannot be pinned to different versions
+        let mut pinned_configs = directory_configs.clone();
+        pinned_configs[1].config.rulesets[0] = RulesetConfig::Detailed(DetailedRulesetConfig {
+            name: "python-security".to_string(),
+            version: Some("2".to_string()),
+            ..Default::default()
+        });
+        assert!(get_rulesets_config(&pinned_configs).is_err());
+    }
+
+    // the extended configuration files are merged in order, then the configuration itself
+    #[test]
+    fn test_read_config_file_with_extends() {
+        let directory =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test/config_file/extends");
+        let config = read_config_file(directory.to_str().unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(None, config.extends);
+        assert_eq!(
+            vec!["python-security", "python-best-practices"],
+            config.rulesets.iter().map(|r| r.name()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("3"), config.rulesets[0].version());
+        assert_eq!(
+            Some(RuleSeverity::Error),
+            config.rulesets[0].config().unwrap().severity
+        );
+        assert_eq!(
+            Some(vec!["tests".to_string(), "generated".to_string()]),
+            config.ignore_paths
+        );
+        assert_eq!(Some(200), config.max_file_size_kb);
+        let effective_config = format_effective_config(&[DirectoryConfig {
+            directory: "".to_string(),
+            path: "static-analysis.datadog.yml".to_string(),
+            config,
+        }])
+        .unwrap();
+        assert!(effective_config.starts_with("# static-analysis.datadog.yml\nrulesets:\n"));
+        assert!(!effective_config.contains("extends"));
+
+        // cycles are detected
+        let directory =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/test/config_file/extends_cycle");
+        let error = format!(
+            "{:#}",
+            read_config_file(directory.to_str().unwrap()).unwrap_err()
+        );
+        assert!(error.contains("cycle in the extended configuration files"));
+        assert!(error.contains("python.yml -> "));
+    }
+
     // No ruleset available in the data means that we have no configuration file
     // whatsoever and we should return None
     #[test]
@@ -0,0 +1,335 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use valico::json_schema;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+/// JSON Schema of the configuration file.
+pub const CONFIG_FILE_SCHEMA: &str = include_str!("static-analysis.datadog.schema.json");
+
+// An error in the configuration file, for the value at a JSON pointer (e.g. /rulesets/0)
+struct SchemaError {
+    pointer: String,
+    message: String,
+}
+
+// Escape a key to use it in a JSON pointer (RFC 6901)
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+enum Frame {
+    // the key is None when the next node is a key
+    Mapping { key: Option<String> },
+    Sequence { index: usize },
+}
+
+// Record the position of the values of a YAML document by JSON pointer. The position of
+// a value in a mapping is the position of its key.
+#[derive(Default)]
+struct PositionsReceiver {
+    stack: Vec<(String, Frame)>,
+    positions: HashMap<String, Marker>,
+}
+
+impl PositionsReceiver {
+    // get the pointer of the next node
+    fn get_pointer(&self) -> String {
+        match self.stack.last() {
+            None => "".to_string(),
+            Some((parent, Frame::Sequence { index })) => format!("{}/{}", parent, index),
+            Some((parent, Frame::Mapping { key: Some(key) })) => {
+                format!("{}/{}", parent, escape_pointer(key))
+            }
+            // a key that is not a scalar, we do not report errors on such keys
+            Some((parent, Frame::Mapping { key: None })) => format!("{}/?", parent),
+        }
+    }
+
+    // get the pointer of a node that starts and record its position
+    fn start_node(&mut self, mark: Marker) -> String {
+        let pointer = self.get_pointer();
+        self.positions.entry(pointer.clone()).or_insert(mark);
+        pointer
+    }
+
+    fn end_node(&mut self) {
+        match self.stack.last_mut() {
+            Some((_, Frame::Sequence { index })) => *index += 1,
+            Some((_, Frame::Mapping { key })) => {
+                *key = match key {
+                    Some(_) => None,
+                    None => Some("?".to_string()),
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for PositionsReceiver {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, ..) => {
+                if let Some((pointer, Frame::Mapping { key: key @ None })) = self.stack.last_mut() {
+                    // the position of a block mapping is the position of its first key
+                    self.positions.entry(pointer.clone()).or_insert(mark);
+                    *key = Some(value);
+                    self.start_node(mark);
+                } else {
+                    self.start_node(mark);
+                    self.end_node();
+                }
+            }
+            Event::Alias(_) => {
+                self.start_node(mark);
+                self.end_node();
+            }
+            Event::MappingStart(..) => {
+                let pointer = self.get_pointer();
+                self.stack.push((pointer, Frame::Mapping { key: None }));
+            }
+            Event::SequenceStart(..) => {
+                let pointer = self.start_node(mark);
+                self.stack.push((pointer, Frame::Sequence { index: 0 }));
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.end_node();
+            }
+            _ => {}
+        }
+    }
+}
+
+// Get the position of the values of a YAML document by JSON pointer. The positions are
+// only used to report errors, an invalid document returns the positions read so far.
+fn get_positions(content: &str) -> HashMap<String, Marker> {
+    let mut receiver = PositionsReceiver::default();
+    let _ = Parser::new(content.chars()).load(&mut receiver, false);
+    receiver.positions
+}
+
+// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Get the known key closest to an unknown key, if it is close enough to be a typo.
+// Underscores are considered the same as dashes (e.g. ignore_paths and ignore-paths).
+fn get_suggestion<'a>(key: &str, known_keys: &[&'a str]) -> Option<&'a str> {
+    let normalized = key.to_lowercase().replace('_', "-");
+    known_keys
+        .iter()
+        .map(|k| (edit_distance(normalized.as_str(), k), *k))
+        .filter(|(distance, _)| *distance <= 2.max(normalized.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, k)| k)
+}
+
+// Follow the reference of a schema (e.g. {"$ref": "#/definitions/paths"})
+fn resolve_schema<'a>(root_schema: &'a Value, schema: &'a Value) -> &'a Value {
+    schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|r| r.strip_prefix('#'))
+        .and_then(|r| root_schema.pointer(r))
+        .map(|s| resolve_schema(root_schema, s))
+        .unwrap_or(schema)
+}
+
+// Get the keys of the objects that are not allowed by the schema. We report them
+// ourselves since the errors of the validator do not include the name of the key.
+fn get_unknown_keys(
+    root_schema: &Value,
+    schema: &Value,
+    value: &Value,
+    pointer: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    let schema = resolve_schema(root_schema, schema);
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, v) in map {
+                let key_pointer = format!("{}/{}", pointer, escape_pointer(key));
+                match (
+                    properties.and_then(|p| p.get(key)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(s), _) => get_unknown_keys(root_schema, s, v, &key_pointer, errors),
+                    (None, Some(Value::Bool(false))) => {
+                        let known_keys: Vec<&str> = properties
+                            .map(|p| p.keys().map(String::as_str).collect())
+                            .unwrap_or_default();
+                        let message = match get_suggestion(key, &known_keys) {
+                            Some(suggestion) => {
+                                format!("unknown key {} (did you mean {}?)", key, suggestion)
+                            }
+                            None => format!("unknown key {}", key),
+                        };
+                        errors.push(SchemaError {
+                            pointer: key_pointer,
+                            message,
+                        });
+                    }
+                    (None, Some(s @ Value::Object(_))) => {
+                        get_unknown_keys(root_schema, s, v, &key_pointer, errors)
+                    }
+                    (None, _) => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(s) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    let item_pointer = format!("{}/{}", pointer, index);
+                    get_unknown_keys(root_schema, s, item, &item_pointer, errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Get the errors of the validator. The errors for the additional properties of objects
+// with unknown keys are not reported since the unknown keys are already reported.
+fn get_validation_errors(
+    schema: Value,
+    value: &Value,
+    unknown_keys: &[SchemaError],
+) -> Result<Vec<SchemaError>> {
+    let mut scope = json_schema::Scope::new();
+    let schema = scope
+        .compile_and_return(schema, false)
+        .map_err(|e| anyhow!("invalid configuration file schema: {:?}", e))?;
+    Ok(schema
+        .validate(value)
+        .errors
+        .iter()
+        .filter(|e| {
+            e.get_code() != "properties"
+                || !unknown_keys
+                    .iter()
+                    .any(|u| u.pointer.rsplit_once('/').map(|(p, _)| p) == Some(e.get_path()))
+        })
+        .map(|e| SchemaError {
+            pointer: e.get_path().to_string(),
+            message: match e.get_detail() {
+                Some(detail) => format!("{}: {}", e.get_title(), detail),
+                None => e.get_title().to_string(),
+            },
+        })
+        .collect())
+}
+
+// Get the position (line and column, starting at 1) of the value of an error, or of
+// its closest parent if the value is missing.
+fn get_error_position(
+    error: &SchemaError,
+    positions: &HashMap<String, Marker>,
+) -> Option<(usize, usize)> {
+    let mut pointer = error.pointer.as_str();
+    loop {
+        if let Some(mark) = positions.get(pointer) {
+            return Some((mark.line(), mark.col() + 1));
+        }
+        pointer = &pointer[..pointer.rfind('/')?];
+    }
+}
+
+/// Validate the content of a configuration file against the schema of the configuration
+/// file (see `CONFIG_FILE_SCHEMA`). The error lists all the problems of the file with
+/// their position in the file.
+pub fn validate_config_file(content: &str) -> Result<()> {
+    let value: Value =
+        serde_yaml::from_str(content).context("error when parsing the configuration file")?;
+    if value.is_null() {
+        return Err(anyhow!("the configuration file is empty"));
+    }
+    let schema: Value = serde_json::from_str(CONFIG_FILE_SCHEMA)?;
+
+    let mut errors = vec![];
+    get_unknown_keys(&schema, &schema, &value, "", &mut errors);
+    let validation_errors = get_validation_errors(schema, &value, &errors)?;
+    errors.extend(validation_errors);
+    if errors.is_empty() {
+        return Ok(());
+    }
+
+    let positions = get_positions(content);
+    let mut errors: Vec<(Option<(usize, usize)>, String)> = errors
+        .into_iter()
+        .map(|e| (get_error_position(&e, &positions), e.message))
+        .collect();
+    errors.sort();
+    let messages: Vec<String> = errors
+        .into_iter()
+        .map(|(position, message)| match position {
+            Some((line, column)) => format!("  line {}, column {}: {}", line, column, message),
+            None => format!("  {}", message),
+        })
+        .collect();
+    Err(anyhow!(
+        "invalid configuration file:\n{}",
+        messages.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_positions() {
+        let data = r#"
+rulesets:
+  - python-security
+  - name: python-best-practices
+    rules:
+      "a/b": {enabled: false}
+ignore-paths: [foo]
+"#;
+        let positions = get_positions(data);
+        let position = |pointer: &str| {
+            positions
+                .get(pointer)
+                .map(|m| (m.line(), m.col() + 1))
+                .unwrap()
+        };
+        assert_eq!((2, 1), position("/rulesets"));
+        assert_eq!((3, 5), position("/rulesets/0"));
+        assert_eq!((4, 5), position("/rulesets/1"));
+        assert_eq!((4, 5), position("/rulesets/1/name"));
+        assert_eq!((6, 7), position("/rulesets/1/rules/a~1b"));
+        assert_eq!((6, 15), position("/rulesets/1/rules/a~1b/enabled"));
+        assert_eq!((7, 1), position("/ignore-paths"));
+        assert_eq!((7, 16), position("/ignore-paths/0"));
+    }
+
+    #[test]
+    fn test_get_suggestion() {
+        let known_keys = vec!["rulesets", "ignore-paths", "max-file-size-kb"];
+        assert_eq!(
+            Some("ignore-paths"),
+            get_suggestion("ignore_paths", &known_keys)
+        );
+        assert_eq!(Some("rulesets"), get_suggestion("ruleset", &known_keys));
+        assert_eq!(
+            Some("max-file-size-kb"),
+            get_suggestion("max_file_size", &known_keys)
+        );
+        assert_eq!(None, get_suggestion("foo", &known_keys));
+    }
+}
@@ -1,3 +1,11 @@
 pub static DATADOG_CONFIG_FILE_WITHOUT_PREFIX: &str = "static-analysis.datadog";
 
 pub static DEFAULT_MAX_FILE_SIZE_KB: u64 = 200;
+
+pub static DATADOG_LOCKFILE: &str = "static-analysis.datadog.lock";
+
+// how long rulesets from the API are used without checking if they changed
+pub static DEFAULT_CACHE_TTL_SEC: u64 = 3600;
+
+// version of the baseline files, changed when the fingerprints of the violations change
+pub static BASELINE_VERSION: u32 = 1;
@@ -1,15 +1,20 @@
 use kernel::model::rule::RuleResult;
+use std::collections::HashMap;
 
-pub fn generate_csv_results(rule_results: &Vec<RuleResult>) -> String {
+// The config_files parameter is the configuration file used for each file, if any.
+pub fn generate_csv_results(
+    rule_results: &Vec<RuleResult>,
+    config_files: &HashMap<String, String>,
+) -> String {
     let mut result = String::new();
     result.push_str(
-        "filename,rule,category,severity,message,start_line,start_col,end_line,end_col\n",
+        "filename,rule,category,severity,message,start_line,start_col,end_line,end_col,fingerprint,config_file\n",
     );
     for r in rule_results {
         for v in &r.violations {
             result.push_str(
                 format!(
-                    "{},{},{},{},{},{},{},{},{}\n",
+                    "{},{},{},{},{},{},{},{},{},{},{}\n",
                     r.filename,
                     r.rule_name,
                     v.category,
@@ -18,7 +23,12 @@ pub fn generate_csv_results(rule_results: &Vec<RuleResult>) -> String {
                     v.start.line,
                     v.start.col,
                     v.end.line,
-                    v.end.col
+                    v.end.col,
+                    v.fingerprint.as_deref().unwrap_or_default(),
+                    config_files
+                        .get(&r.filename)
+                        .map(String::as_str)
+                        .unwrap_or_default()
                 )
                 .as_str(),
             );
@@ -39,12 +49,12 @@ mod tests {
     // execution time must be more than 0
     #[test]
     fn test_export_csv() {
-        let res_no_result = generate_csv_results(&vec![]);
+        let res_no_result = generate_csv_results(&vec![], &HashMap::new());
         assert_eq!(
             res_no_result,
-            "filename,rule,category,severity,message,start_line,start_col,end_line,end_col\n"
+            "filename,rule,category,severity,message,start_line,start_col,end_line,end_col,fingerprint,config_file\n"
         );
-        let res_with_result = generate_csv_results(&vec![RuleResult {
+        let rule_results = vec![RuleResult {
             rule_name: "myrule".to_string(),
             filename: "filename".to_string(),
             violations: vec![Violation {
@@ -54,12 +64,22 @@ mod tests {
                 severity: RuleSeverity::Error,
                 category: RuleCategory::Performance,
                 fixes: vec![],
+                fingerprint: Some("fingerprint".to_string()),
             }],
             errors: vec![],
             execution_error: None,
             output: None,
             execution_time_ms: 10,
-        }]);
-        assert_eq!(res_with_result, "filename,rule,category,severity,message,start_line,start_col,end_line,end_col\nfilename,myrule,performance,error,message,10,12,12,10\n");
+        }];
+        let res_with_result = generate_csv_results(&rule_results, &HashMap::new());
+        assert_eq!(res_with_result, "filename,rule,category,severity,message,start_line,start_col,end_line,end_col,fingerprint,config_file\nfilename,myrule,performance,error,message,10,12,12,10,fingerprint,\n");
+        let config_files = HashMap::from([(
+            "filename".to_string(),
+            "static-analysis.datadog.yml".to_string(),
+        )]);
+        let res_with_config_file = generate_csv_results(&rule_results, &config_files);
+        assert!(res_with_config_file.ends_with(
+            "filename,myrule,performance,error,message,10,12,12,10,fingerprint,static-analysis.datadog.yml\n"
+        ));
     }
 }
@@ -1,22 +1,14 @@
 use std::env;
 
 use anyhow::{anyhow, Context, Result};
-use kernel::model::rule::Rule;
 use kernel::model::ruleset::RuleSet;
+use reqwest::{header, StatusCode, Url};
+use serde::{Deserialize, Serialize};
 
 use crate::model::datadog_api::ApiResponse;
 
 const DEFAULT_DATADOG_SITE: &str = "datadoghq.com";
 
-// Get all the rules from different rulesets from Datadog
-pub fn get_rules_from_rulesets(rulesets_name: &[String]) -> Result<Vec<Rule>> {
-    let mut rules: Vec<Rule> = Vec::new();
-    for ruleset_name in rulesets_name {
-        rules.extend(get_ruleset(ruleset_name)?.rules);
-    }
-    Ok(rules)
-}
-
 // Get environment variables for Datadog. First try to get the variables
 // prefixed with DD_ and then, try DATADOG_.
 // If nothing works, just returns an error.
@@ -36,17 +28,75 @@ pub fn get_datadog_variable_value(variable: &str) -> anyhow::Result<String> {
 // it connects to the API using the DD_SITE, DD_APP_KEY and DD_API_KEY and retrieve
 // the rulesets. We then extract all the rulesets
 pub fn get_ruleset(ruleset_name: &str) -> Result<RuleSet> {
-    let site = get_datadog_variable_value("SITE").unwrap_or(DEFAULT_DATADOG_SITE.to_string());
+    get_ruleset_with_version(ruleset_name, None)
+}
+
+// get one ruleset at datadog, at a given version if specified (otherwise, the
+// latest version of the ruleset).
+pub fn get_ruleset_with_version(ruleset_name: &str, version: Option<&str>) -> Result<RuleSet> {
+    let url = get_ruleset_url(get_datadog_site().as_str(), ruleset_name, version)?;
+    match request_ruleset(url.as_str(), &RulesetValidators::default())? {
+        RulesetResponse::Modified { ruleset, .. } => Ok(ruleset),
+        RulesetResponse::NotModified => Err(anyhow!("unexpected response from the server")),
+    }
+}
+
+// the Datadog site to use, from DD_SITE or DATADOG_SITE
+pub fn get_datadog_site() -> String {
+    get_datadog_variable_value("SITE").unwrap_or(DEFAULT_DATADOG_SITE.to_string())
+}
+
+// URL of a ruleset in the API of a Datadog site. The name is a segment of the path and
+// the version a parameter of the query, both are encoded so that they cannot change the
+// endpoint that is queried.
+pub fn get_ruleset_url(site: &str, ruleset_name: &str, version: Option<&str>) -> Result<String> {
+    // path_segments_mut ignores these segments instead of encoding them
+    if ruleset_name.is_empty() || ruleset_name == "." || ruleset_name == ".." {
+        return Err(anyhow!("invalid ruleset name {}", ruleset_name));
+    }
+    let mut url =
+        Url::parse(format!("https://api.{}/api/v2/static-analysis/rulesets", site).as_str())
+            .with_context(|| format!("invalid Datadog site {}", site))?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("invalid Datadog site {}", site))?
+        .push(ruleset_name);
+    if let Some(v) = version {
+        url.query_pairs_mut().append_pair("version", v);
+    }
+    Ok(url.to_string())
+}
+
+// validators of a ruleset fetched previously, sent to only get the ruleset
+// if it changed since then.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+pub struct RulesetValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub enum RulesetResponse {
+    Modified {
+        ruleset: RuleSet,
+        validators: RulesetValidators,
+    },
+    NotModified,
+}
+
+// query a ruleset in the API. If validators are specified, the server only sends the
+// ruleset if it changed.
+pub fn request_ruleset(url: &str, validators: &RulesetValidators) -> Result<RulesetResponse> {
     let app_key = get_datadog_variable_value("APP_KEY");
     let api_key = get_datadog_variable_value("API_KEY");
-    let url = format!(
-        "https://api.{}/api/v2/static-analysis/rulesets/{}",
-        site, ruleset_name
-    );
 
-    let request_builder = reqwest::blocking::Client::new()
+    let mut request_builder = reqwest::blocking::Client::new()
         .get(url)
         .header("Content-Type", "application/json");
+    if let Some(etag) = &validators.etag {
+        request_builder = request_builder.header(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &validators.last_modified {
+        request_builder = request_builder.header(header::IF_MODIFIED_SINCE, last_modified);
+    }
 
     // only add datadog credentials if both app-key and api-keys are defined.
     let request_builder_with_auth = match (app_key, api_key) {
@@ -56,10 +106,54 @@ pub fn get_ruleset(ruleset_name: &str) -> Result<RuleSet> {
         _ => request_builder,
     };
 
-    Ok(request_builder_with_auth
+    let response = request_builder_with_auth
         .send()
-        .context("error querying rulesets")?
-        .json::<ApiResponse>()
-        .context("error when parsing the server response")?
-        .into_ruleset())
+        .context("error querying rulesets")?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(RulesetResponse::NotModified);
+    }
+    let response = response
+        .error_for_status()
+        .context("error querying rulesets")?;
+    let get_header = |name: header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let validators = RulesetValidators {
+        etag: get_header(header::ETAG),
+        last_modified: get_header(header::LAST_MODIFIED),
+    };
+    Ok(RulesetResponse::Modified {
+        ruleset: response
+            .json::<ApiResponse>()
+            .context("error when parsing the server response")?
+            .into_ruleset(),
+        validators,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_ruleset_url() {
+        assert_eq!(
+            "https://api.datadoghq.com/api/v2/static-analysis/rulesets/python-security",
+            get_ruleset_url("datadoghq.com", "python-security", None).unwrap()
+        );
+        assert_eq!(
+            "https://api.datadoghq.com/api/v2/static-analysis/rulesets/python-security?version=1.0+beta%261",
+            get_ruleset_url("datadoghq.com", "python-security", Some("1.0 beta&1")).unwrap()
+        );
+        assert_eq!(
+            "https://api.datadoghq.com/api/v2/static-analysis/rulesets/..%2Fusers%3Fa%23b",
+            get_ruleset_url("datadoghq.com", "../users?a#b", None).unwrap()
+        );
+        assert!(get_ruleset_url("datadoghq.com", "..", None).is_err());
+        assert!(get_ruleset_url("datadog hq.com", "python-security", None).is_err());
+    }
 }
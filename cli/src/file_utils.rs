@@ -1,132 +1,211 @@
+use crate::language_detection::{get_languages_for_file, LanguageDetectionOptions};
 use crate::model::cli_configuration::CliConfiguration;
 use anyhow::Result;
+use git2::{Repository, StatusOptions};
 use glob_match::glob_match;
+use ignore::WalkBuilder;
 use kernel::model::common::Language;
 use std::fs;
-use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
-
-static FILE_EXTENSIONS_PER_LANGUAGE_LIST: &[(Language, &[&str])] = &[
-    (Language::JavaScript, &["js", "jsx"]),
-    (Language::Dockerfile, &["docker", "dockerfile"]),
-    (Language::Python, &["py", "py3"]),
-    (Language::Rust, &["rs"]),
-    (Language::TypeScript, &["ts", "tsx"]),
-];
-
-// get all extensions for a language.
-fn get_extensions_for_language(language: &Language) -> Option<Vec<String>> {
-    for fe in FILE_EXTENSIONS_PER_LANGUAGE_LIST {
-        if fe.0 == *language {
-            let extensions = fe.1.to_vec();
-            return Some(extensions.iter().map(|x| x.to_string()).collect());
-        }
-    }
-    None
+
+// How to select the files to analyze in a directory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileSelectionOptions {
+    // do not analyze the files ignored by git: the .gitignore files of the directory, its
+    // subdirectories and its parent directories and the .git/info/exclude file
+    pub use_gitignore: bool,
+    // get the files tracked by git instead of walking the directory, when the directory
+    // is a git repository
+    pub use_git_index: bool,
+    // with use_git_index, also get the files not tracked by git (except the files ignored
+    // by git if use_gitignore is set)
+    pub include_untracked: bool,
 }
 
-// Read the .gitignore file in a directory and return the lines that are not commented
-// or empty.
-// We ignore pattern that start with # (comments) or contains ! (cause repositories
-// not being included and totally skipped).
-pub fn read_files_from_gitignore_internal(path: &PathBuf) -> Result<Vec<String>> {
-    if path.exists() {
-        let lines: Vec<String> = read_to_string(path)?
-            .lines()
-            .map(String::from)
-            .filter(|v| !v.starts_with('#'))
-            .filter(|v| !v.contains('!'))
-            .filter(|v| !v.is_empty())
-            .collect();
-        return Ok(lines);
+// Get the path of a directory relative to the working directory of the git repository
+// that contains it, with / as separator (as the paths of git). The path is empty for the
+// root of the repository and None for a bare repository.
+pub fn get_repository_prefix(repository: &Repository, directory: &str) -> Option<String> {
+    let workdir = fs::canonicalize(repository.workdir()?).ok()?;
+    let directory = fs::canonicalize(directory).ok()?;
+    let relative_path = directory.strip_prefix(workdir).ok()?;
+    relative_path
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<Vec<&str>>>()
+        .map(|c| c.join("/"))
+}
+
+// Rebase a path relative to a git repository onto the directory of the prefix (see
+// `get_repository_prefix`). Returns None when the path is not in this directory.
+pub fn strip_repository_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    if prefix.is_empty() {
+        return Some(path);
     }
-    Ok(vec![])
+    path.strip_prefix(prefix)?.strip_prefix('/')
 }
 
-pub fn read_files_from_gitignore(source_directory: &str) -> Result<Vec<String>> {
-    let gitignore_path = Path::new(source_directory).join(".gitignore");
-    read_files_from_gitignore_internal(&gitignore_path)
+// get the files of a git repository in the directory of the prefix (see
+// `get_repository_prefix`), relative to this directory: the files of the index and
+// optionally the untracked files.
+fn get_files_from_git_repository(
+    repository: &Repository,
+    prefix: &str,
+    options: &FileSelectionOptions,
+) -> Result<Vec<String>> {
+    let mut files: Vec<String> = repository
+        .index()?
+        .iter()
+        .filter_map(|entry| String::from_utf8(entry.path).ok())
+        .collect();
+
+    if options.include_untracked {
+        let mut status_options = StatusOptions::new();
+        status_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(!options.use_gitignore)
+            .recurse_ignored_dirs(!options.use_gitignore)
+            .exclude_submodules(true);
+        let statuses = repository.statuses(Some(&mut status_options))?;
+        files.extend(
+            statuses
+                .iter()
+                .filter(|s| s.status().is_wt_new() || s.status().is_ignored())
+                .filter_map(|s| s.path().map(String::from)),
+        );
+    }
+
+    let mut files: Vec<String> = files
+        .iter()
+        .filter_map(|f| strip_repository_prefix(f, prefix).map(String::from))
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
 }
 
 // get the files to analyze from the directory. This function walks the directory
-// to analyze recursively and gets all the files.
-pub fn get_files(directory: &str, paths_to_ignore: &[String]) -> Result<Vec<PathBuf>> {
+// to analyze recursively and gets all the files. The directories ignored by git (if
+// enabled) or by a prefix of the paths to ignore are not walked.
+// With use_git_index, the files are the files tracked by git when the directory is
+// in a git repository.
+pub fn get_files(
+    directory: &str,
+    paths_to_ignore: &[String],
+    options: &FileSelectionOptions,
+) -> Result<Vec<PathBuf>> {
+    if options.use_git_index {
+        let repository = Repository::discover(directory).ok();
+        if let Some((repository, prefix)) = repository.and_then(|r| {
+            let prefix = get_repository_prefix(&r, directory)?;
+            Some((r, prefix))
+        }) {
+            // files deleted from the working tree, symlinks and submodules are not analyzed
+            return Ok(
+                get_files_from_git_repository(&repository, &prefix, options)?
+                    .iter()
+                    .filter(|f| !is_ignored_path(f, paths_to_ignore))
+                    .map(|f| Path::new(directory).join(f))
+                    .filter(|f| fs::symlink_metadata(f).is_ok_and(|m| m.is_file()))
+                    .collect(),
+            );
+        }
+    }
+
     let mut files_to_return: Vec<PathBuf> = vec![];
 
     // This is the directory that contains the .git files, we do not need to keep them.
-    let git_directory = format!("{}/.git", &directory);
-
-    for entry in WalkDir::new(directory) {
-        let dir_entry = entry?;
-        let entry = dir_entry.path();
-
-        // we only include if this is a file and not a symlink
-        // we should NEVER follow symlink for security reason (an attacker could then
-        // attempt to add a symlink outside the repo and read content outside of the
-        // repo with a custom rule.
-        let mut should_include = entry.is_file() && !entry.is_symlink();
-        let path_buf = entry.to_path_buf();
+    let git_directory = Path::new(directory).join(".git");
+    let directory_path = PathBuf::from(directory);
+    let prefixes_to_ignore: Vec<String> = paths_to_ignore
+        .iter()
+        .filter(|p| !p.is_empty())
+        .cloned()
+        .collect();
 
-        // check if the path should be ignored by a glob or not.
-        for path_to_ignore in paths_to_ignore {
-            // skip empty path to ignore
-            if path_to_ignore.is_empty() {
-                continue;
+    // we never follow symlinks for security reason (an attacker could then attempt to
+    // add a symlink outside the repo and read content outside of the repo with a
+    // custom rule).
+    let walker = WalkBuilder::new(directory)
+        .standard_filters(false)
+        .git_ignore(options.use_gitignore)
+        .git_exclude(options.use_gitignore)
+        .require_git(false)
+        .parents(options.use_gitignore)
+        .follow_links(false)
+        .filter_entry(move |entry| {
+            let path = entry.path();
+            if path == git_directory {
+                return false;
             }
-
-            let relative_path_str = path_buf
-                .strip_prefix(directory)
-                .ok()
-                .and_then(|p| p.to_str())
-                .ok_or_else(|| anyhow::Error::msg("should get the path"))?;
-            if glob_match(path_to_ignore.as_str(), relative_path_str) {
-                should_include = false;
+            let is_directory = entry.file_type().is_some_and(|t| t.is_dir());
+            let relative_path = path.strip_prefix(&directory_path).unwrap_or(path);
+            !is_directory
+                || relative_path.as_os_str().is_empty()
+                || !prefixes_to_ignore
+                    .iter()
+                    .any(|p| relative_path.starts_with(Path::new(p.as_str())))
+        })
+        .build();
+
+    for entry in walker {
+        let dir_entry = match entry {
+            Ok(e) => e,
+            // an invalid pattern in a .gitignore file does not prevent the analysis
+            Err(e) if e.is_partial() => {
+                eprintln!("warning: error when reading ignored files: {}", e);
+                continue;
             }
+            Err(e) => return Err(e.into()),
+        };
+        let entry = dir_entry.path();
 
-            let relative_path_res = path_buf.strip_prefix(directory);
-
-            if let Ok(relative_path) = relative_path_res {
-                if relative_path.starts_with(Path::new(path_to_ignore.as_str())) {
-                    should_include = false;
-                }
-            }
+        // we only include if this is a file and not a symlink
+        if !dir_entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
         }
 
-        // do not include the git directory.
-        if entry.starts_with(git_directory.as_str()) {
-            should_include = false;
+        // check if the path should be ignored by a glob or not.
+        let relative_path_str = entry
+            .strip_prefix(directory)
+            .ok()
+            .and_then(|p| p.to_str())
+            .ok_or_else(|| anyhow::Error::msg("should get the path"))?;
+        if is_ignored_path(relative_path_str, paths_to_ignore) {
+            continue;
         }
 
-        if should_include {
-            files_to_return.push(entry.to_path_buf());
-        }
+        files_to_return.push(entry.to_path_buf());
     }
     Ok(files_to_return)
 }
 
-// filter files to analyze for a language. It will filter the files based on the prefix or suffix.
-pub fn filter_files_for_language(files: &[PathBuf], language: &Language) -> Vec<PathBuf> {
-    let extensions_opt = get_extensions_for_language(language);
-
-    if extensions_opt.is_none() {
-        return vec![];
-    }
-
-    let extensions = extensions_opt.unwrap();
+// Indicates if a path relative to the analyzed directory is ignored: it matches one of
+// the paths to ignore, either as a glob or as a prefix (same as `get_files`).
+pub fn is_ignored_path(relative_path: &str, paths_to_ignore: &[String]) -> bool {
+    paths_to_ignore.iter().filter(|p| !p.is_empty()).any(|p| {
+        glob_match(p.as_str(), relative_path)
+            || Path::new(relative_path).starts_with(Path::new(p.as_str()))
+    })
+}
 
-    let result = files
+// filter files to analyze for a language. The language of each file is detected
+// using its name, extension and content (see `get_languages_for_file`).
+pub fn filter_files_for_language(
+    files: &[PathBuf],
+    language: &Language,
+    directory: &str,
+    language_detection_options: &LanguageDetectionOptions,
+) -> Vec<PathBuf> {
+    files
         .iter()
-        .filter(|p| match p.extension() {
-            Some(ext) => match ext.to_str() {
-                Some(e) => extensions.contains(&e.to_string()),
-                None => false,
-            },
-            None => false,
+        .filter(|p| {
+            let relative_path = p.strip_prefix(directory).unwrap_or(p);
+            get_languages_for_file(p, relative_path, language_detection_options).contains(language)
         })
         .cloned()
-        .collect();
-    result
+        .collect()
 }
 
 pub fn filter_files_by_size(files: &[PathBuf], configuration: &CliConfiguration) -> Vec<PathBuf> {
@@ -158,31 +237,11 @@ pub fn filter_files_by_size(files: &[PathBuf], configuration: &CliConfiguration)
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::config_file::{HeaderFilesLanguage, LanguageMapping};
+    use crate::test_utils::create_temp_dir;
     use kernel::model::common::OutputFormat::Sarif;
-    use std::collections::HashMap;
     use std::path::Path;
 
-    #[test]
-    fn get_gitignore_exists() {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("resources/test/gitignore/test1");
-        let file_list = read_files_from_gitignore_internal(&d);
-        assert!(file_list.is_ok());
-        let fl = file_list.unwrap();
-        assert_eq!(85, fl.len());
-        // check it contains the values of the file
-        assert!(fl.contains(&"ddtrace/appsec/_ddwaf.cpp".to_string()));
-        // make sure it does not contains lines with !
-        assert!(!fl.contains(&"!.env".to_string()));
-        assert_eq!(
-            0,
-            fl.iter()
-                .filter(|v| v.starts_with("#"))
-                .collect::<Vec<&String>>()
-                .len()
-        )
-    }
-
     /// Filter files bigger than one kilobyte and make sure files
     /// less than one kilobyte are not being filtered.
     #[test]
@@ -203,6 +262,8 @@ mod tests {
             num_cpus: 2, // of cpus to use for parallelism
             rules: vec![],
             max_file_size_kb: 1,
+            language_detection: LanguageDetectionOptions::default(),
+            config_files: vec![],
         };
         assert_eq!(0, filter_files_by_size(&files1, &cli_configuration).len());
 
@@ -213,16 +274,6 @@ mod tests {
         assert_eq!(1, filter_files_by_size(&files2, &cli_configuration).len());
     }
 
-    #[test]
-    fn get_gitignore_do_not_exists() {
-        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        d.push("resources/test/gitignore/test-do-not-exists");
-        let file_list = read_files_from_gitignore_internal(&d);
-        assert!(file_list.is_ok());
-        let fl = file_list.unwrap();
-        assert!(fl.is_empty());
-    }
-
     // make sure we can get the list of rules from a directory and that the
     // ignore-paths correctly works when we pass a glob.
     #[test]
@@ -238,6 +289,7 @@ mod tests {
         let files = get_files(
             current_path.display().to_string().as_str(),
             &empty_paths_to_ignore,
+            &FileSelectionOptions::default(),
         );
         assert!(files.is_ok());
         let f = &files.unwrap();
@@ -250,7 +302,11 @@ mod tests {
 
         // now, we add one path to ignore
         let ignore_paths = vec!["**/src/**/lib.rs".to_string()];
-        let files = get_files(current_path.display().to_string().as_str(), &ignore_paths);
+        let files = get_files(
+            current_path.display().to_string().as_str(),
+            &ignore_paths,
+            &FileSelectionOptions::default(),
+        );
         assert!(files.is_ok());
         let f = &files.unwrap();
         let find_file: Vec<String> = f
@@ -274,7 +330,11 @@ mod tests {
 
         // now, we one path to ignore, we should filter.
         let ignore_paths = vec!["src".to_string()];
-        let files = get_files(current_path.display().to_string().as_str(), &ignore_paths);
+        let files = get_files(
+            current_path.display().to_string().as_str(),
+            &ignore_paths,
+            &FileSelectionOptions::default(),
+        );
         assert!(files.is_ok());
         let f = &files.unwrap();
         let find_file: Vec<String> = f
@@ -286,7 +346,11 @@ mod tests {
 
         // now, we add the complete path to ignore, we should filter.
         let ignore_paths = vec!["src/lib.rs".to_string()];
-        let files = get_files(current_path.display().to_string().as_str(), &ignore_paths);
+        let files = get_files(
+            current_path.display().to_string().as_str(),
+            &ignore_paths,
+            &FileSelectionOptions::default(),
+        );
         assert!(files.is_ok());
         let f = &files.unwrap();
         let find_file: Vec<String> = f
@@ -298,7 +362,11 @@ mod tests {
 
         // now, we add another directory that is totally different, we should not filter.
         let ignore_paths = vec!["foo".to_string()];
-        let files = get_files(current_path.display().to_string().as_str(), &ignore_paths);
+        let files = get_files(
+            current_path.display().to_string().as_str(),
+            &ignore_paths,
+            &FileSelectionOptions::default(),
+        );
         assert!(files.is_ok());
         let f = &files.unwrap();
         let find_file: Vec<String> = f
@@ -309,23 +377,178 @@ mod tests {
         assert_eq!(1, find_file.len()); // correctly filtered
     }
 
-    // check that we have the correct number of extensions for each language we support.
+    // the files ignored by git are not analyzed: .gitignore files in all directories,
+    // .git/info/exclude, negations and anchored patterns.
     #[test]
-    fn get_extensions_for_language_all_languages() {
-        let mut extensions_per_languages: HashMap<Language, usize> = HashMap::new();
-        extensions_per_languages.insert(Language::JavaScript, 2);
-        extensions_per_languages.insert(Language::Python, 2);
-        extensions_per_languages.insert(Language::Rust, 1);
-        extensions_per_languages.insert(Language::TypeScript, 2);
-
-        for (l, e) in extensions_per_languages {
-            assert_eq!(
-                get_extensions_for_language(&l)
-                    .expect("have extensions")
-                    .len(),
-                e
-            );
+    fn get_list_of_files_with_gitignore() {
+        let temp_dir = create_temp_dir(&[
+            (".gitignore", "/build\n*.log\n!keep.log\ndocs/\n"),
+            ("sub/.gitignore", "generated.py\n"),
+            (".git/info/exclude", "secret.py\n"),
+            ("main.py", ""),
+            ("build/main.py", ""),
+            ("docs/main.py", ""),
+            ("debug.log", ""),
+            ("keep.log", ""),
+            ("secret.py", ""),
+            ("sub/main.py", ""),
+            ("sub/generated.py", ""),
+            ("sub/build/main.py", ""),
+            ("sub/docs/main.py", ""),
+        ]);
+        let directory = temp_dir.path();
+
+        let get_relative_paths = |use_gitignore: bool| {
+            let mut files: Vec<String> = get_files(
+                directory.to_str().unwrap(),
+                &[],
+                &FileSelectionOptions {
+                    use_gitignore,
+                    ..FileSelectionOptions::default()
+                },
+            )
+            .unwrap()
+            .iter()
+            .map(|p| p.strip_prefix(directory).unwrap().display().to_string())
+            .collect();
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            vec![
+                ".gitignore",
+                "keep.log",
+                "main.py",
+                "sub/.gitignore",
+                "sub/build/main.py",
+                "sub/main.py"
+            ],
+            get_relative_paths(true)
+        );
+        assert_eq!(12, get_relative_paths(false).len());
+
+        // the .gitignore files of the parent directories and .git/info/exclude also
+        // apply when analyzing a subdirectory
+        std::fs::write(directory.join("sub/secret.py"), "").unwrap();
+        let subdirectory = directory.join("sub");
+        let mut files: Vec<String> = get_files(
+            subdirectory.to_str().unwrap(),
+            &[],
+            &FileSelectionOptions {
+                use_gitignore: true,
+                ..FileSelectionOptions::default()
+            },
+        )
+        .unwrap()
+        .iter()
+        .map(|p| p.strip_prefix(&subdirectory).unwrap().display().to_string())
+        .collect();
+        files.sort();
+        assert_eq!(vec![".gitignore", "build/main.py", "main.py"], files);
+    }
+
+    // the files tracked by git are analyzed and optionally the untracked files that are
+    // not ignored. A directory that is not a git repository is walked.
+    #[test]
+    fn get_list_of_files_from_git_index() {
+        let temp_dir = create_temp_dir(&[
+            (".gitignore", "*.log\n"),
+            ("src/main.py", ""),
+            ("src/deleted.py", ""),
+            ("untracked.py", ""),
+            ("debug.log", ""),
+        ]);
+        let directory = temp_dir.path();
+
+        let get_relative_paths = |options: &FileSelectionOptions| {
+            let mut files: Vec<String> = get_files(directory.to_str().unwrap(), &[], options)
+                .unwrap()
+                .iter()
+                .map(|p| p.strip_prefix(directory).unwrap().display().to_string())
+                .collect();
+            files.sort();
+            files
+        };
+        let options = FileSelectionOptions {
+            use_gitignore: true,
+            use_git_index: true,
+            include_untracked: false,
+        };
+
+        // not a git repository
+        assert_eq!(
+            vec![
+                ".gitignore",
+                "src/deleted.py",
+                "src/main.py",
+                "untracked.py"
+            ],
+            get_relative_paths(&options)
+        );
+
+        let repository = Repository::init(directory).unwrap();
+        let mut index = repository.index().unwrap();
+        for f in [".gitignore", "src/main.py", "src/deleted.py"] {
+            index.add_path(Path::new(f)).unwrap();
         }
+        index.write().unwrap();
+        std::fs::remove_file(directory.join("src/deleted.py")).unwrap();
+
+        assert_eq!(
+            vec![".gitignore", "src/main.py"],
+            get_relative_paths(&options)
+        );
+        assert_eq!(
+            vec![".gitignore", "src/main.py", "untracked.py"],
+            get_relative_paths(&FileSelectionOptions {
+                include_untracked: true,
+                ..options
+            })
+        );
+        assert_eq!(
+            vec![".gitignore", "debug.log", "src/main.py", "untracked.py"],
+            get_relative_paths(&FileSelectionOptions {
+                use_gitignore: false,
+                include_untracked: true,
+                ..options
+            })
+        );
+
+        // a subdirectory of the repository only has the files under it
+        std::fs::write(directory.join("src/untracked.py"), "").unwrap();
+        let subdirectory = directory.join("src");
+        let mut files: Vec<String> = get_files(
+            subdirectory.to_str().unwrap(),
+            &[],
+            &FileSelectionOptions {
+                include_untracked: true,
+                ..options
+            },
+        )
+        .unwrap()
+        .iter()
+        .map(|p| p.strip_prefix(&subdirectory).unwrap().display().to_string())
+        .collect();
+        files.sort();
+        assert_eq!(vec!["main.py", "untracked.py"], files);
+    }
+
+    #[test]
+    fn test_is_ignored_path() {
+        let ignore_paths = vec![
+            "".to_string(),
+            "services/a/**/test/**".to_string(),
+            "services/b".to_string(),
+        ];
+        assert!(is_ignored_path("services/a/test/main.py", &ignore_paths));
+        assert!(is_ignored_path(
+            "services/a/src/test/main.py",
+            &ignore_paths
+        ));
+        assert!(is_ignored_path("services/b/main.py", &ignore_paths));
+        assert!(!is_ignored_path("services/bb/main.py", &ignore_paths));
+        assert!(!is_ignored_path("services/a/main.py", &ignore_paths));
     }
 
     #[test]
@@ -336,13 +559,44 @@ mod tests {
         let files = get_files(
             current_path.display().to_string().as_str(),
             &empty_paths_to_ignore,
+            &FileSelectionOptions::default(),
         );
         assert!(files.is_ok());
         let files = &files.unwrap();
+        let directory = current_path.display().to_string();
+        let options = LanguageDetectionOptions::default();
         assert_eq!(
             0,
-            filter_files_for_language(files, &Language::TypeScript).len()
+            filter_files_for_language(files, &Language::TypeScript, &directory, &options).len()
+        );
+        assert_ne!(
+            0,
+            filter_files_for_language(files, &Language::Rust, &directory, &options).len()
+        );
+    }
+
+    // the custom mappings are matched against the path relative to the directory
+    #[test]
+    fn test_filter_files_for_language_relative_path() {
+        let files = vec![
+            PathBuf::from("/repo/scripts/deploy.jsm"),
+            PathBuf::from("/repo/src/index.js"),
+            PathBuf::from("/repo/Dockerfile.prod"),
+        ];
+        let options = LanguageDetectionOptions {
+            header_files_language: HeaderFilesLanguage::C,
+            language_mappings: vec![LanguageMapping {
+                pattern: "scripts/*.jsm".to_string(),
+                language: Language::JavaScript,
+            }],
+        };
+        assert_eq!(
+            2,
+            filter_files_for_language(&files, &Language::JavaScript, "/repo", &options).len()
+        );
+        assert_eq!(
+            1,
+            filter_files_for_language(&files, &Language::Dockerfile, "/repo", &options).len()
         );
-        assert_ne!(0, filter_files_for_language(files, &Language::Rust).len());
     }
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use kernel::model::common::Position;
+use kernel::model::violation::{Edit, EditType, Violation};
+
+// get the offset (in bytes) of a position in the code. Lines and columns start at 1
+// and columns are in bytes, like the positions reported by tree-sitter.
+fn get_offset(code: &str, position: &Position) -> Result<usize> {
+    let invalid_position = || anyhow!("invalid {}", position);
+    let line_index = usize::try_from(position.line)?
+        .checked_sub(1)
+        .ok_or_else(invalid_position)?;
+    let col_index = usize::try_from(position.col)?
+        .checked_sub(1)
+        .ok_or_else(invalid_position)?;
+
+    let mut lines = code.split_inclusive('\n');
+    let mut line_start = 0;
+    for _ in 0..line_index {
+        line_start += lines.next().ok_or_else(invalid_position)?.len();
+    }
+    let line_length = lines.next().map_or(0, str::len);
+    let offset = line_start + col_index;
+    if col_index > line_length || !code.is_char_boundary(offset) {
+        return Err(invalid_position());
+    }
+    Ok(offset)
+}
+
+/// Apply a list of edits on the code. The edits must not overlap.
+pub fn apply_edits(code: &str, edits: &[Edit]) -> Result<String> {
+    // (start, end, content) of each edit, in bytes
+    let mut replacements = edits
+        .iter()
+        .map(|edit| {
+            let start = get_offset(code, &edit.start)?;
+            let end = match edit.edit_type {
+                EditType::Add => start,
+                EditType::Remove | EditType::Update => {
+                    let end_position = edit
+                        .end
+                        .as_ref()
+                        .ok_or_else(|| anyhow!("no end position for edit at {}", edit.start))?;
+                    get_offset(code, end_position)?
+                }
+            };
+            let content = match edit.edit_type {
+                EditType::Remove => "",
+                EditType::Add | EditType::Update => edit.content.as_deref().unwrap_or_default(),
+            };
+            if end < start {
+                return Err(anyhow!("edit ends before its start at {}", edit.start));
+            }
+            Ok((start, end, content))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // apply the edits from the end of the code so that offsets stay valid
+    replacements.sort_by_key(|(start, end, _)| (*start, *end));
+    let mut result = code.to_string();
+    let mut previous_start = code.len();
+    for (start, end, content) in replacements.into_iter().rev() {
+        if end > previous_start {
+            return Err(anyhow!("overlapping edits at offset {}", start));
+        }
+        result.replace_range(start..end, content);
+        previous_start = start;
+    }
+    Ok(result)
+}
+
+/// Apply the fixes of the violations on the code. When a violation has several fixes,
+/// they are alternatives and only the first one is applied.
+pub fn apply_fixes(code: &str, violations: &[Violation]) -> Result<String> {
+    let edits: Vec<Edit> = violations
+        .iter()
+        .filter_map(|v| v.fixes.first())
+        .flat_map(|f| f.edits.clone())
+        .collect();
+    apply_edits(code, &edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(
+        edit_type: EditType,
+        start: (u32, u32),
+        end: Option<(u32, u32)>,
+        content: &str,
+    ) -> Edit {
+        Edit {
+            start: Position {
+                line: start.0,
+                col: start.1,
+            },
+            end: end.map(|(line, col)| Position { line, col }),
+            edit_type,
+            content: Some(content.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_apply_edits() {
+        let code = "def foo(arg1):\n    pass\n";
+        let edits = vec![
+            edit(EditType::Update, (1, 5), Some((1, 8)), "bar"),
+            edit(EditType::Add, (2, 5), None, "return "),
+            edit(EditType::Remove, (2, 9), Some((2, 9)), ""),
+            edit(EditType::Update, (1, 9), Some((1, 13)), "arg2"),
+        ];
+        assert_eq!(
+            "def bar(arg2):\n    return pass\n",
+            apply_edits(code, &edits).unwrap()
+        );
+        assert_eq!(code, apply_edits(code, &[]).unwrap());
+    }
+
+    #[test]
+    fn test_apply_edits_invalid() {
+        let code = "def foo(arg1):\n    pass\n";
+        let overlapping = vec![
+            edit(EditType::Update, (1, 5), Some((1, 8)), "bar"),
+            edit(EditType::Remove, (1, 6), Some((1, 10)), ""),
+        ];
+        assert!(apply_edits(code, &overlapping).is_err());
+        let no_end = vec![edit(EditType::Update, (1, 5), None, "bar")];
+        assert!(apply_edits(code, &no_end).is_err());
+        let out_of_code = vec![edit(EditType::Add, (1, 50), None, "bar")];
+        assert!(apply_edits(code, &out_of_code).is_err());
+    }
+}
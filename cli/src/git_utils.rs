@@ -0,0 +1,324 @@
+use anyhow::{anyhow, Context, Result};
+use git2::{Delta, Diff, DiffFindOptions, DiffOptions, Repository};
+use kernel::model::rule::RuleResult;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::file_utils::{get_repository_prefix, strip_repository_prefix};
+use crate::model::git_diff::GitDiff;
+
+// get the files added or modified in a diff (deleted files are not included)
+fn get_changed_files(diff: &Diff) -> BTreeSet<String> {
+    diff.deltas()
+        .filter(|d| d.status() != Delta::Deleted)
+        .filter_map(|d| d.new_file().path())
+        .filter_map(|p| p.to_str())
+        .map(String::from)
+        .collect()
+}
+
+// get the files of a diff that did not exist before
+fn get_new_files(diff: &Diff) -> BTreeSet<String> {
+    diff.deltas()
+        .filter(|d| matches!(d.status(), Delta::Added | Delta::Untracked))
+        .filter_map(|d| d.new_file().path())
+        .filter_map(|p| p.to_str())
+        .map(String::from)
+        .collect()
+}
+
+// get the lines added or modified in each file of a diff. The diff must not have
+// context lines so that the hunks only contain the changed lines.
+fn get_changed_lines(diff: &Diff) -> Result<BTreeMap<String, Vec<(u32, u32)>>> {
+    let mut changed_lines: BTreeMap<String, Vec<(u32, u32)>> = BTreeMap::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |delta, hunk| {
+            // hunks that only delete lines do not change the lines of the new file
+            if let (Some(path), true) = (
+                delta.new_file().path().and_then(|p| p.to_str()),
+                hunk.new_lines() > 0,
+            ) {
+                changed_lines
+                    .entry(path.to_string())
+                    .or_default()
+                    .push((hunk.new_start(), hunk.new_start() + hunk.new_lines() - 1));
+            }
+            true
+        }),
+        None,
+    )?;
+    Ok(changed_lines)
+}
+
+// Get the changes of the repository of a directory since a base revision (e.g. a branch
+// or a commit). As for `git diff <base>...HEAD`, the changes are compared to the merge
+// base of the base revision and HEAD so that only the changes of the branch are reported.
+// If include_working_tree is set, the changes not committed yet are also included.
+// The directory may be a subdirectory of the repository: only the changes of its files
+// are reported, with paths relative to the directory.
+pub fn get_git_diff(directory: &str, base: &str, include_working_tree: bool) -> Result<GitDiff> {
+    let repository = Repository::discover(directory)
+        .with_context(|| format!("{} is not in a git repository", directory))?;
+    let prefix = get_repository_prefix(&repository, directory).ok_or_else(|| {
+        anyhow!(
+            "{} is not in the working directory of a git repository",
+            directory
+        )
+    })?;
+    let head_commit = repository
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .context("cannot get the HEAD commit")?;
+    let base_object = repository
+        .revparse_single(base)
+        .with_context(|| format!("cannot find the revision {}", base))?;
+    let base_commit_id = repository
+        .merge_base(base_object.peel_to_commit()?.id(), head_commit.id())
+        .with_context(|| format!("no common ancestor between {} and HEAD", base))?;
+    let base_tree = repository.find_commit(base_commit_id)?.tree()?;
+
+    let mut diff_options = DiffOptions::new();
+    diff_options
+        .context_lines(0)
+        .include_untracked(include_working_tree)
+        .recurse_untracked_dirs(include_working_tree)
+        .show_untracked_content(include_working_tree);
+    let mut diff = if include_working_tree {
+        repository.diff_tree_to_workdir_with_index(Some(&base_tree), Some(&mut diff_options))?
+    } else {
+        repository.diff_tree_to_tree(
+            Some(&base_tree),
+            Some(&head_commit.tree()?),
+            Some(&mut diff_options),
+        )?
+    };
+    // a file that is renamed is not a new file, only its changed lines are reported
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let rebase_files = |files: BTreeSet<String>| -> BTreeSet<String> {
+        files
+            .iter()
+            .filter_map(|f| strip_repository_prefix(f, &prefix).map(String::from))
+            .collect()
+    };
+    Ok(GitDiff {
+        base_commit: base_commit_id.to_string(),
+        head_commit: head_commit.id().to_string(),
+        changed_files: rebase_files(get_changed_files(&diff)),
+        new_files: rebase_files(get_new_files(&diff)),
+        changed_lines: get_changed_lines(&diff)?
+            .into_iter()
+            .filter_map(|(f, lines)| {
+                strip_repository_prefix(&f, &prefix).map(|f| (f.to_string(), lines))
+            })
+            .collect(),
+    })
+}
+
+// Keep only the violations on the lines added or modified since the base commit: the
+// range of lines of the violation must contain a changed line. The violations of the
+// files that did not exist in the base commit are removed unless keep_new_files is set;
+// all their lines are changed, so the kept ones are filtered on their lines like the others.
+pub fn filter_violations_on_changed_lines(
+    rule_results: &mut [RuleResult],
+    git_diff: &GitDiff,
+    keep_new_files: bool,
+) {
+    for rule_result in rule_results.iter_mut() {
+        if !keep_new_files && git_diff.new_files.contains(&rule_result.filename) {
+            rule_result.violations.clear();
+            continue;
+        }
+        let changed_lines = git_diff
+            .changed_lines
+            .get(&rule_result.filename)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        rule_result.violations.retain(|v| {
+            changed_lines
+                .iter()
+                .any(|(start, end)| v.start.line <= *end && v.end.line >= *start)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_temp_dir, write_files};
+    use git2::{IndexAddOption, Signature};
+    use kernel::model::common::PositionBuilder;
+    use kernel::model::rule::{RuleCategory, RuleResultBuilder, RuleSeverity};
+    use kernel::model::violation::ViolationBuilder;
+
+    // commit all the files of the working tree
+    fn commit_all(repository: &Repository, message: &str) {
+        let mut index = repository.index().unwrap();
+        index
+            .add_all(["*"].iter(), IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@datadoghq.com").unwrap();
+        let parents = match repository.head() {
+            Ok(head) => vec![head.peel_to_commit().unwrap()],
+            Err(_) => vec![],
+        };
+        let parents: Vec<_> = parents.iter().collect();
+        repository
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                message,
+                &tree,
+                &parents,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_git_diff() {
+        let lines: Vec<String> = (1..=10).map(|i| format!("value{} = {}\n", i, i)).collect();
+        let temp_dir = create_temp_dir(&[
+            ("src/modified.py", "a = 1\n"),
+            ("src/deleted.py", "b = 1\n"),
+            ("src/unchanged.py", "c = 1\n"),
+            ("src/moved.py", lines.concat().as_str()),
+        ]);
+        let directory = temp_dir.path();
+        let repository = Repository::init(directory).unwrap();
+        let write = |path: &str, content: &str| write_files(directory, &[(path, content)]);
+        commit_all(&repository, "base");
+        let base_commit = repository.head().unwrap().target().unwrap().to_string();
+
+        write("src/modified.py", "a = 2\n");
+        write("src/added.py", "d = 1\n");
+        std::fs::remove_file(directory.join("src/deleted.py")).unwrap();
+        std::fs::remove_file(directory.join("src/moved.py")).unwrap();
+        let mut lines = lines.clone();
+        lines[4] = "value5 = 42\n".to_string();
+        write("src/renamed.py", lines.concat().as_str());
+        commit_all(&repository, "head");
+        let head_commit = repository.head().unwrap().target().unwrap().to_string();
+        write("src/uncommitted.py", "e = 1\n");
+
+        let directory_str = directory.to_str().unwrap();
+        let diff = get_git_diff(directory_str, "HEAD~1", false).unwrap();
+        assert_eq!(base_commit, diff.base_commit);
+        assert_eq!(head_commit, diff.head_commit);
+        assert_eq!(
+            BTreeSet::from([
+                "src/added.py".to_string(),
+                "src/modified.py".to_string(),
+                "src/renamed.py".to_string()
+            ]),
+            diff.changed_files
+        );
+        assert_eq!(BTreeSet::from(["src/added.py".to_string()]), diff.new_files);
+        assert_eq!(
+            BTreeMap::from([
+                ("src/added.py".to_string(), vec![(1, 1)]),
+                ("src/modified.py".to_string(), vec![(1, 1)]),
+                ("src/renamed.py".to_string(), vec![(5, 5)])
+            ]),
+            diff.changed_lines
+        );
+
+        let diff = get_git_diff(directory_str, base_commit.as_str(), true).unwrap();
+        assert_eq!(
+            BTreeSet::from([
+                "src/added.py".to_string(),
+                "src/modified.py".to_string(),
+                "src/renamed.py".to_string(),
+                "src/uncommitted.py".to_string()
+            ]),
+            diff.changed_files
+        );
+        assert_eq!(
+            Some(&vec![(1, 1)]),
+            diff.changed_lines.get("src/uncommitted.py")
+        );
+
+        // the paths are relative to the analyzed subdirectory
+        write("root.py", "f = 1\n");
+        let diff = get_git_diff(directory.join("src").to_str().unwrap(), "HEAD~1", true).unwrap();
+        assert_eq!(
+            BTreeSet::from([
+                "added.py".to_string(),
+                "modified.py".to_string(),
+                "renamed.py".to_string(),
+                "uncommitted.py".to_string()
+            ]),
+            diff.changed_files
+        );
+        assert_eq!(
+            BTreeSet::from(["added.py".to_string(), "uncommitted.py".to_string()]),
+            diff.new_files
+        );
+        assert_eq!(Some(&vec![(5, 5)]), diff.changed_lines.get("renamed.py"));
+
+        assert!(get_git_diff(directory_str, "does-not-exist", false).is_err());
+    }
+
+    #[test]
+    fn test_filter_violations_on_changed_lines() {
+        let violation = |start: u32, end: u32| {
+            ViolationBuilder::default()
+                .start(
+                    PositionBuilder::default()
+                        .line(start)
+                        .col(1)
+                        .build()
+                        .unwrap(),
+                )
+                .end(PositionBuilder::default().line(end).col(1).build().unwrap())
+                .message("violation".to_string())
+                .severity(RuleSeverity::Error)
+                .category(RuleCategory::BestPractices)
+                .fixes(vec![])
+                .build()
+                .unwrap()
+        };
+        let rule_result = |filename: &str| {
+            RuleResultBuilder::default()
+                .rule_name("ruleset/rule".to_string())
+                .filename(filename.to_string())
+                .violations(vec![violation(1, 2), violation(4, 6), violation(10, 10)])
+                .errors(vec![])
+                .execution_error(None)
+                .output(None)
+                .execution_time_ms(0)
+                .build()
+                .unwrap()
+        };
+        let git_diff = GitDiff {
+            new_files: BTreeSet::from(["new.py".to_string()]),
+            changed_lines: BTreeMap::from([
+                ("new.py".to_string(), vec![(1, 5)]),
+                ("modified.py".to_string(), vec![(3, 3), (6, 8)]),
+            ]),
+            ..GitDiff::default()
+        };
+        let get_lines = |rule_results: &[RuleResult]| -> Vec<Vec<u32>> {
+            rule_results
+                .iter()
+                .map(|r| r.violations.iter().map(|v| v.start.line).collect())
+                .collect()
+        };
+
+        let mut rule_results = vec![
+            rule_result("modified.py"),
+            rule_result("new.py"),
+            rule_result("unchanged.py"),
+        ];
+        filter_violations_on_changed_lines(&mut rule_results, &git_diff, false);
+        assert_eq!(vec![vec![4], vec![], vec![]], get_lines(&rule_results));
+
+        let mut rule_results = vec![rule_result("modified.py"), rule_result("new.py")];
+        filter_violations_on_changed_lines(&mut rule_results, &git_diff, true);
+        assert_eq!(vec![vec![4], vec![1, 4]], get_lines(&rule_results));
+    }
+}
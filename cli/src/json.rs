@@ -0,0 +1,69 @@
+use kernel::model::rule::RuleResult;
+use serde::Serialize;
+use std::collections::HashMap;
+
+// a result of the JSON report: the result of the rule and the configuration file used
+// for its file, if any.
+#[derive(Serialize)]
+struct JsonRuleResult<'a> {
+    #[serde(flatten)]
+    rule_result: &'a RuleResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    config_file: Option<&'a String>,
+}
+
+// Generate the JSON report. The config_files parameter is the configuration file used
+// for each file, if any.
+pub fn generate_json_results(
+    rule_results: &[RuleResult],
+    config_files: &HashMap<String, String>,
+) -> serde_json::Result<String> {
+    let results: Vec<JsonRuleResult> = rule_results
+        .iter()
+        .map(|rule_result| JsonRuleResult {
+            rule_result,
+            config_file: config_files.get(&rule_result.filename),
+        })
+        .collect();
+    serde_json::to_string(&results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::model::rule::RuleResultBuilder;
+    use serde_json::Value;
+
+    #[test]
+    fn test_generate_json_results() {
+        let rule_result = |filename: &str| {
+            RuleResultBuilder::default()
+                .rule_name("myrule".to_string())
+                .filename(filename.to_string())
+                .violations(vec![])
+                .errors(vec![])
+                .execution_error(None)
+                .output(None)
+                .execution_time_ms(10)
+                .build()
+                .unwrap()
+        };
+        let config_files = HashMap::from([(
+            "services/a/main.py".to_string(),
+            "services/a/static-analysis.datadog.yml".to_string(),
+        )]);
+        let report = generate_json_results(
+            &[rule_result("services/a/main.py"), rule_result("main.py")],
+            &config_files,
+        )
+        .unwrap();
+        let results: Vec<Value> = serde_json::from_str(report.as_str()).unwrap();
+        assert_eq!("services/a/main.py", results[0]["filename"]);
+        assert_eq!("myrule", results[0]["rule_name"]);
+        assert_eq!(
+            "services/a/static-analysis.datadog.yml",
+            results[0]["config_file"]
+        );
+        assert!(results[1].get("config_file").is_none());
+    }
+}
@@ -0,0 +1,308 @@
+use crate::model::config_file::{HeaderFilesLanguage, LanguageMapping};
+use glob_match::glob_match;
+use kernel::analysis::dynamic_grammars::get_dynamic_grammars;
+use kernel::model::common::Language;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+// Extension of header files that are shared between C and C++.
+const HEADER_FILE_EXTENSION: &str = "h";
+
+// maximum number of bytes we read to find the shebang of a file.
+const SHEBANG_MAX_LENGTH: u64 = 256;
+
+static FILE_EXTENSIONS_PER_LANGUAGE_LIST: &[(Language, &[&str])] = &[
+    (Language::Bash, &["sh", "bash"]),
+    (Language::C, &["c"]),
+    (Language::Cpp, &["cc", "cpp", "cxx", "hpp"]),
+    (Language::Csharp, &["cs"]),
+    (Language::Dockerfile, &["docker", "dockerfile"]),
+    (Language::Go, &["go"]),
+    (Language::Java, &["java"]),
+    (Language::JavaScript, &["js", "jsx"]),
+    (Language::Json, &["json"]),
+    (Language::Php, &["php"]),
+    (Language::Python, &["py", "py3"]),
+    (Language::Ruby, &["rb", "rake"]),
+    (Language::Rust, &["rs"]),
+    (Language::TypeScript, &["ts", "tsx"]),
+];
+
+// files that are always of a given language, regardless of their extension.
+static FILENAMES_PER_LANGUAGE_LIST: &[(Language, &[&str])] = &[
+    (Language::Dockerfile, &["Dockerfile"]),
+    (Language::Ruby, &["Gemfile", "Rakefile"]),
+];
+
+// glob patterns matched against the name of the file (e.g. Dockerfile.prod).
+static FILENAME_PATTERNS_PER_LANGUAGE_LIST: &[(Language, &[&str])] =
+    &[(Language::Dockerfile, &["Dockerfile.*", "*.Dockerfile"])];
+
+// interpreters found in the shebang of files without extension. Versions are
+// removed from the interpreter before matching (e.g. python3 matches python).
+static SHEBANG_INTERPRETERS_PER_LANGUAGE_LIST: &[(Language, &[&str])] = &[
+    (Language::Bash, &["bash", "sh"]),
+    (Language::JavaScript, &["node"]),
+    (Language::Php, &["php"]),
+    (Language::Python, &["python"]),
+    (Language::Ruby, &["ruby"]),
+];
+
+/// Options used to detect the language of a file. They come from the
+/// configuration file of the repository.
+#[derive(Clone, Debug, Default)]
+pub struct LanguageDetectionOptions {
+    pub header_files_language: HeaderFilesLanguage,
+    pub language_mappings: Vec<LanguageMapping>,
+}
+
+// get the interpreter from a shebang line. It handles both the direct form
+// (`#!/bin/bash`) and the env form (`#!/usr/bin/env bash`).
+fn parse_shebang_interpreter(line: &str) -> Option<String> {
+    let command = line.strip_prefix("#!")?;
+    let mut parts = command.split_whitespace();
+    let program = Path::new(parts.next()?).file_name()?.to_str()?;
+
+    if program == "env" {
+        // skip the options and variables passed to env (e.g. `env -S bash -e`)
+        parts
+            .find(|p| !p.starts_with('-') && !p.contains('='))
+            .map(ToString::to_string)
+    } else {
+        Some(program.to_string())
+    }
+}
+
+// read the first line of a file and get the interpreter from its shebang, if any.
+fn get_shebang_interpreter(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let mut first_line = String::new();
+    BufReader::new(file.take(SHEBANG_MAX_LENGTH))
+        .read_line(&mut first_line)
+        .ok()?;
+    parse_shebang_interpreter(first_line.as_str())
+}
+
+// get the languages for an interpreter, ignoring its version (python3.11 -> python).
+fn get_languages_for_interpreter(interpreter: &str) -> Vec<Language> {
+    let interpreter_without_version =
+        interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    SHEBANG_INTERPRETERS_PER_LANGUAGE_LIST
+        .iter()
+        .filter(|(_, interpreters)| interpreters.contains(&interpreter_without_version))
+        .map(|(language, _)| *language)
+        .collect()
+}
+
+// get the languages used for header files according to the configuration.
+fn get_languages_for_header_files(header_files_language: &HeaderFilesLanguage) -> Vec<Language> {
+    match header_files_language {
+        HeaderFilesLanguage::C => vec![Language::C],
+        HeaderFilesLanguage::Cpp => vec![Language::Cpp],
+        HeaderFilesLanguage::Both => vec![Language::C, Language::Cpp],
+    }
+}
+
+/// Detect the languages of a file. Most files have a single language, but header
+/// files can be analyzed as both C and C++. The detection is done in this order:
+///  1. the custom mappings from the configuration file (the first match wins)
+///  2. well-known filenames and filename patterns (e.g. `Dockerfile.prod`)
+///  3. the extension of the file
+///  4. the shebang, for files without extension
+///  5. the file patterns of the grammars loaded at runtime
+///
+/// The `relative_path` is the path of the file from the root of the repository
+/// and is used to match the custom mappings.
+pub fn get_languages_for_file(
+    path: &Path,
+    relative_path: &Path,
+    options: &LanguageDetectionOptions,
+) -> Vec<Language> {
+    if let Some(relative_path_str) = relative_path.to_str() {
+        if let Some(mapping) = options
+            .language_mappings
+            .iter()
+            .find(|m| glob_match(m.pattern.as_str(), relative_path_str))
+        {
+            return vec![mapping.language];
+        }
+    }
+
+    let filename = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
+
+    for (language, filenames) in FILENAMES_PER_LANGUAGE_LIST {
+        if filenames.contains(&filename) {
+            return vec![*language];
+        }
+    }
+
+    for (language, patterns) in FILENAME_PATTERNS_PER_LANGUAGE_LIST {
+        if patterns.iter().any(|p| glob_match(p, filename)) {
+            return vec![*language];
+        }
+    }
+
+    let languages = match path.extension().map(|e| e.to_str()) {
+        Some(Some(HEADER_FILE_EXTENSION)) => {
+            get_languages_for_header_files(&options.header_files_language)
+        }
+        Some(Some(extension)) => FILE_EXTENSIONS_PER_LANGUAGE_LIST
+            .iter()
+            .filter(|(_, extensions)| extensions.contains(&extension))
+            .map(|(language, _)| *language)
+            .collect(),
+        Some(None) => vec![],
+        None => get_shebang_interpreter(path)
+            .map(|i| get_languages_for_interpreter(i.as_str()))
+            .unwrap_or_default(),
+    };
+    if !languages.is_empty() {
+        return languages;
+    }
+
+    get_dynamic_grammars()
+        .into_iter()
+        .filter(|g| g.file_patterns.iter().any(|p| glob_match(p, filename)))
+        .map(|g| Language::Dynamic(g.name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::model::common::ALL_LANGUAGES;
+    use std::path::PathBuf;
+
+    fn detect(path: &str, options: &LanguageDetectionOptions) -> Vec<Language> {
+        get_languages_for_file(Path::new(path), Path::new(path), options)
+    }
+
+    // every language we support must be detected with at least one extension.
+    #[test]
+    fn test_all_languages_have_extensions() {
+        for language in ALL_LANGUAGES {
+            assert!(
+                FILE_EXTENSIONS_PER_LANGUAGE_LIST
+                    .iter()
+                    .any(|(l, extensions)| l == language && !extensions.is_empty()),
+                "no extension for language {}",
+                language
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_languages_for_file_extensions() {
+        let options = LanguageDetectionOptions::default();
+        assert_eq!(vec![Language::Go], detect("src/main.go", &options));
+        assert_eq!(vec![Language::Java], detect("src/Main.java", &options));
+        assert_eq!(vec![Language::Csharp], detect("src/Program.cs", &options));
+        assert_eq!(vec![Language::Json], detect("package.json", &options));
+        assert_eq!(vec![Language::TypeScript], detect("src/app.tsx", &options));
+        assert!(detect("README.md", &options).is_empty());
+    }
+
+    #[test]
+    fn test_get_languages_for_file_filenames() {
+        let options = LanguageDetectionOptions::default();
+        assert_eq!(vec![Language::Dockerfile], detect("Dockerfile", &options));
+        assert_eq!(
+            vec![Language::Dockerfile],
+            detect("deploy/Dockerfile.prod", &options)
+        );
+        assert_eq!(
+            vec![Language::Dockerfile],
+            detect("deploy/api.Dockerfile", &options)
+        );
+        assert_eq!(vec![Language::Ruby], detect("Gemfile", &options));
+        assert!(detect("Makefile", &options).is_empty());
+    }
+
+    // header files are analyzed as C, C++ or both depending on the configuration.
+    #[test]
+    fn test_get_languages_for_file_header_files() {
+        let mut options = LanguageDetectionOptions::default();
+        assert_eq!(vec![Language::C], detect("include/foo.h", &options));
+        assert_eq!(vec![Language::Cpp], detect("include/foo.hpp", &options));
+        options.header_files_language = HeaderFilesLanguage::Cpp;
+        assert_eq!(vec![Language::Cpp], detect("include/foo.h", &options));
+        options.header_files_language = HeaderFilesLanguage::Both;
+        assert_eq!(
+            vec![Language::C, Language::Cpp],
+            detect("include/foo.h", &options)
+        );
+    }
+
+    // custom mappings take precedence over everything else
+    #[test]
+    fn test_get_languages_for_file_custom_mappings() {
+        let options = LanguageDetectionOptions {
+            header_files_language: HeaderFilesLanguage::C,
+            language_mappings: vec![
+                LanguageMapping {
+                    pattern: "**/*.jsm".to_string(),
+                    language: Language::JavaScript,
+                },
+                LanguageMapping {
+                    pattern: "legacy/**/*.h".to_string(),
+                    language: Language::Cpp,
+                },
+            ],
+        };
+        assert_eq!(vec![Language::JavaScript], detect("lib/foo.jsm", &options));
+        assert_eq!(vec![Language::Cpp], detect("legacy/src/foo.h", &options));
+        assert_eq!(vec![Language::C], detect("src/foo.h", &options));
+    }
+
+    #[test]
+    fn test_parse_shebang_interpreter() {
+        assert_eq!(
+            Some("bash".to_string()),
+            parse_shebang_interpreter("#!/bin/bash")
+        );
+        assert_eq!(
+            Some("sh".to_string()),
+            parse_shebang_interpreter("#!/usr/bin/env sh\n")
+        );
+        assert_eq!(
+            Some("bash".to_string()),
+            parse_shebang_interpreter("#!/usr/bin/env -S bash -e")
+        );
+        assert_eq!(
+            Some("python3".to_string()),
+            parse_shebang_interpreter("#! /usr/bin/python3")
+        );
+        assert_eq!(None, parse_shebang_interpreter("# comment"));
+        assert_eq!(None, parse_shebang_interpreter("#!"));
+    }
+
+    #[test]
+    fn test_get_languages_for_interpreter() {
+        assert_eq!(
+            vec![Language::Python],
+            get_languages_for_interpreter("python3.11")
+        );
+        assert_eq!(vec![Language::Bash], get_languages_for_interpreter("sh"));
+        assert_eq!(
+            vec![Language::JavaScript],
+            get_languages_for_interpreter("node")
+        );
+        assert!(get_languages_for_interpreter("perl").is_empty());
+    }
+
+    // scripts without extensions are detected using their shebang
+    #[test]
+    fn test_get_languages_for_file_shebang() {
+        let options = LanguageDetectionOptions::default();
+        let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        d.push("resources/test/shebang");
+        assert_eq!(
+            vec![Language::Bash],
+            get_languages_for_file(&d.join("deploy"), Path::new("deploy"), &options)
+        );
+        assert!(
+            get_languages_for_file(&d.join("README"), Path::new("README"), &options).is_empty()
+        );
+    }
+}
@@ -1,8 +1,23 @@
+pub mod baseline;
 pub mod config_file;
+pub mod config_file_schema;
 pub mod constants;
 pub mod csv;
 pub mod datadog_utils;
 pub mod file_utils;
+pub mod fix_utils;
+pub mod git_utils;
+pub mod json;
+pub mod language_detection;
+pub mod lockfile;
 pub mod model;
+pub mod rule_config;
+pub mod rule_lint_utils;
+pub mod rule_test_report;
+pub mod rule_test_utils;
 pub mod rule_utils;
+pub mod ruleset_cache;
 pub mod sarif;
+
+#[cfg(test)]
+mod test_utils;
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Context, Result};
+use kernel::model::ruleset::RuleSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::constants;
+use crate::model::config_file::RulesetConfig;
+use crate::model::lockfile::{LockedRuleset, Lockfile};
+
+fn get_lockfile_path(path: &str) -> PathBuf {
+    Path::new(path).join(constants::DATADOG_LOCKFILE)
+}
+
+// Read the lockfile of the repository. If the file does not exist, we return Ok(None).
+pub fn read_lockfile(path: &str) -> Result<Option<Lockfile>> {
+    let lockfile_path = get_lockfile_path(path);
+    let contents = match fs::read_to_string(&lockfile_path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("error when reading the lockfile"),
+    };
+    Ok(Some(
+        serde_yaml::from_str(&contents).context("error when parsing the lockfile")?,
+    ))
+}
+
+// Write the lockfile of the repository and return its path.
+pub fn write_lockfile(path: &str, lockfile: &Lockfile) -> Result<PathBuf> {
+    let lockfile_path = get_lockfile_path(path);
+    fs::write(&lockfile_path, serde_yaml::to_string(lockfile)?)
+        .context("error when writing the lockfile")?;
+    Ok(lockfile_path)
+}
+
+fn lock_ruleset(ruleset: &RuleSet, version: Option<&str>) -> LockedRuleset {
+    LockedRuleset {
+        version: version.map(|v| v.to_string()),
+        checksum: ruleset.compute_checksum(),
+        rules: ruleset
+            .rules
+            .iter()
+            .map(|r| (r.name.clone(), r.compute_content_checksum()))
+            .collect(),
+    }
+}
+
+// Generate the lockfile for the rulesets fetched for the configuration
+pub fn generate_lockfile(rulesets_config: &[RulesetConfig], rulesets: &[RuleSet]) -> Lockfile {
+    Lockfile {
+        rulesets: rulesets
+            .iter()
+            .map(|ruleset| {
+                let version = rulesets_config
+                    .iter()
+                    .find(|c| c.name() == ruleset.name)
+                    .and_then(|c| c.version());
+                (ruleset.name.clone(), lock_ruleset(ruleset, version))
+            })
+            .collect(),
+    }
+}
+
+// Check that a ruleset matches the checksum it is pinned to in the configuration.
+pub fn check_pinned_ruleset(ruleset_config: &RulesetConfig, ruleset: &RuleSet) -> Result<()> {
+    match ruleset_config.checksum() {
+        Some(expected) if expected != ruleset.compute_checksum() => Err(anyhow!(
+            "ruleset {} does not match its pinned checksum (expected {}, got {})",
+            ruleset.name,
+            expected,
+            ruleset.compute_checksum()
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Get the differences between the lockfile and the fetched rulesets, one message
+// per difference. An empty list means that the rules match the lockfile.
+pub fn get_lockfile_differences(lockfile: &Lockfile, rulesets: &[RuleSet]) -> Vec<String> {
+    let mut differences = vec![];
+    for ruleset in rulesets {
+        let locked = match lockfile.rulesets.get(&ruleset.name) {
+            Some(l) => l,
+            None => {
+                differences.push(format!("ruleset {} is not in the lockfile", ruleset.name));
+                continue;
+            }
+        };
+        let fetched = lock_ruleset(ruleset, None);
+        if fetched.checksum == locked.checksum {
+            continue;
+        }
+        for (rule, checksum) in &fetched.rules {
+            match locked.rules.get(rule) {
+                None => differences.push(format!("rule {} is not in the lockfile", rule)),
+                Some(c) if c != checksum => differences.push(format!(
+                    "rule {} changed (checksum {}, locked {})",
+                    rule, checksum, c
+                )),
+                Some(_) => {}
+            }
+        }
+        for rule in locked.rules.keys() {
+            if !fetched.rules.contains_key(rule) {
+                differences.push(format!("rule {} was removed", rule));
+            }
+        }
+    }
+    for ruleset in lockfile.rulesets.keys() {
+        if !rulesets.iter().any(|r| &r.name == ruleset) {
+            differences.push(format!("ruleset {} is locked but not used", ruleset));
+        }
+    }
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config_file::DetailedRulesetConfig;
+    use kernel::model::common::Language;
+    use kernel::model::rule::{Rule, RuleCategory, RuleSeverity, RuleType};
+    use std::collections::HashMap;
+
+    fn rule(name: &str, code_base64: &str) -> Rule {
+        Rule {
+            name: name.to_string(),
+            short_description_base64: None,
+            description_base64: None,
+            category: RuleCategory::BestPractices,
+            severity: RuleSeverity::Warning,
+            language: Language::Python,
+            rule_type: RuleType::TreeSitterQuery,
+            entity_checked: None,
+            code_base64: code_base64.to_string(),
+            checksum: "".to_string(),
+            pattern: None,
+            tree_sitter_query_base64: None,
+            variables: HashMap::new(),
+            tests: vec![],
+            signature: None,
+        }
+    }
+
+    fn ruleset(rules: Vec<Rule>) -> RuleSet {
+        RuleSet {
+            name: "python-security".to_string(),
+            description: None,
+            rules,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_check_pinned_ruleset() {
+        let ruleset = ruleset(vec![rule("python-security/rule1", "Y29kZQ==")]);
+        assert!(check_pinned_ruleset(
+            &RulesetConfig::Name("python-security".to_string()),
+            &ruleset
+        )
+        .is_ok());
+        let pinned = |checksum: String| {
+            RulesetConfig::Detailed(DetailedRulesetConfig {
+                name: "python-security".to_string(),
+                checksum: Some(checksum),
+                ..Default::default()
+            })
+        };
+        assert!(check_pinned_ruleset(&pinned(ruleset.compute_checksum()), &ruleset).is_ok());
+        assert!(check_pinned_ruleset(&pinned("foo".to_string()), &ruleset)
+            .unwrap_err()
+            .to_string()
+            .starts_with("ruleset python-security does not match its pinned checksum"));
+    }
+
+    #[test]
+    fn test_lockfile_differences() {
+        let rulesets_config = vec![RulesetConfig::Detailed(DetailedRulesetConfig {
+            name: "python-security".to_string(),
+            version: Some("3".to_string()),
+            ..Default::default()
+        })];
+        let locked_rulesets = vec![ruleset(vec![
+            rule("python-security/rule1", "Y29kZQ=="),
+            rule("python-security/rule2", "Y29kZQ=="),
+        ])];
+        let lockfile = generate_lockfile(&rulesets_config, &locked_rulesets);
+        assert_eq!(
+            Some("3".to_string()),
+            lockfile.rulesets["python-security"].version
+        );
+
+        // the lockfile survives a round-trip
+        let serialized = serde_yaml::to_string(&lockfile).unwrap();
+        assert_eq!(
+            lockfile,
+            serde_yaml::from_str::<Lockfile>(serialized.as_str()).unwrap()
+        );
+
+        assert!(get_lockfile_differences(&lockfile, &locked_rulesets).is_empty());
+
+        let fetched_rulesets = vec![ruleset(vec![
+            rule("python-security/rule1", "Y29kZTI="),
+            rule("python-security/rule3", "Y29kZQ=="),
+        ])];
+        let differences = get_lockfile_differences(&lockfile, &fetched_rulesets);
+        assert_eq!(3, differences.len());
+        assert!(differences[0].starts_with("rule python-security/rule1 changed"));
+        assert_eq!(
+            "rule python-security/rule3 is not in the lockfile",
+            differences[1]
+        );
+        assert_eq!("rule python-security/rule2 was removed", differences[2]);
+
+        // a change of the severity of a rule is a difference even if its code is the same
+        let mut fetched_rule = rule("python-security/rule1", "Y29kZQ==");
+        fetched_rule.severity = RuleSeverity::Error;
+        let fetched_rulesets = vec![ruleset(vec![
+            fetched_rule,
+            rule("python-security/rule2", "Y29kZQ=="),
+        ])];
+        let differences = get_lockfile_differences(&lockfile, &fetched_rulesets);
+        assert_eq!(1, differences.len());
+        assert!(differences[0].starts_with("rule python-security/rule1 changed"));
+
+        assert_eq!(
+            vec!["ruleset python-security is locked but not used"],
+            get_lockfile_differences(&lockfile, &[])
+        );
+    }
+}
@@ -1,3 +1,7 @@
+pub mod baseline;
 pub mod cli_configuration;
 pub mod config_file;
 pub mod datadog_api;
+pub mod git_diff;
+pub mod lockfile;
+pub mod rule_directory;
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+// a violation recorded in the baseline
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct BaselineViolation {
+    pub filename: String,
+    // line of the violation when the baseline was created, only used to report it
+    pub line: u32,
+    pub rule: String,
+    // see `Violation::compute_fingerprint`
+    pub fingerprint: String,
+}
+
+// the violations that existed when the baseline was created and that are not reported
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct Baseline {
+    // see `constants::BASELINE_VERSION`, 0 for the baselines without version
+    #[serde(default)]
+    pub version: u32,
+    pub violations: Vec<BaselineViolation>,
+}
@@ -1,3 +1,4 @@
+use crate::language_detection::LanguageDetectionOptions;
 use kernel::model::common::OutputFormat;
 use kernel::model::rule::Rule;
 
@@ -15,4 +16,6 @@ pub struct CliConfiguration {
     pub num_cpus: usize, // of cpus to use for parallelism
     pub rules: Vec<Rule>,
     pub max_file_size_kb: u64,
+    pub language_detection: LanguageDetectionOptions, // how to detect the language of files
+    pub config_files: Vec<String>, // configuration files used, relative to the source directory
 }
@@ -1,23 +1,168 @@
 use std::fmt;
 
+use kernel::model::common::Language;
+use kernel::model::rule::RuleSeverity;
 use serde;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// How to classify `.h` files that can be either C or C++ headers.
+#[derive(Copy, Clone, Default, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub enum HeaderFilesLanguage {
+    #[default]
+    #[serde(rename = "c")]
+    C,
+    #[serde(rename = "cpp")]
+    Cpp,
+    #[serde(rename = "both")]
+    Both,
+}
+
+impl fmt::Display for HeaderFilesLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::C => "c",
+            Self::Cpp => "cpp",
+            Self::Both => "both",
+        };
+        write!(f, "{s}")
+    }
+}
+
+// Map all the files matching a glob to a language.
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct LanguageMapping {
+    pub pattern: String,
+    pub language: Language,
+}
+
+// Configuration of a rule, or of all the rules of a ruleset.
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct RuleConfig {
+    // a disabled rule is not executed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    // severity of the violations, instead of the severity reported by the rule
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<RuleSeverity>,
+    // only analyze the paths matching one of these globs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only: Option<Vec<String>>,
+    // do not analyze the paths matching one of these globs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+}
+
+// A ruleset with its options: the version and/or the checksum (see
+// `RuleSet::compute_checksum`) it is pinned to, the configuration of all its rules
+// and the configuration of some rules (by rule name, without the ruleset name).
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct DetailedRulesetConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    #[serde(flatten)]
+    pub config: RuleConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, RuleConfig>,
+}
+
+// A ruleset used by the repository: either only its name or its name with its options.
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+#[serde(untagged)]
+pub enum RulesetConfig {
+    Name(String),
+    Detailed(DetailedRulesetConfig),
+}
+
+impl RulesetConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Name(name) => name,
+            Self::Detailed(d) => d.name.as_str(),
+        }
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            Self::Name(_) => None,
+            Self::Detailed(d) => d.version.as_deref(),
+        }
+    }
+
+    pub fn checksum(&self) -> Option<&str> {
+        match self {
+            Self::Name(_) => None,
+            Self::Detailed(d) => d.checksum.as_deref(),
+        }
+    }
+
+    // configuration of all the rules of the ruleset
+    pub fn config(&self) -> Option<&RuleConfig> {
+        match self {
+            Self::Name(_) => None,
+            Self::Detailed(d) => Some(&d.config),
+        }
+    }
+
+    // configuration of a rule of the ruleset, from the name of the rule without the
+    // ruleset name
+    pub fn rule_config(&self, rule_name: &str) -> Option<&RuleConfig> {
+        match self {
+            Self::Name(_) => None,
+            Self::Detailed(d) => d.rules.get(rule_name),
+        }
+    }
+}
 
 // the configuration file from the repository
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Clone, Default, Deserialize, Debug, Serialize, PartialEq)]
 pub struct ConfigFile {
-    pub rulesets: Vec<String>,
-    #[serde(rename(serialize = "ignore-paths", deserialize = "ignore-paths"))]
+    // configuration files this configuration is based on, relative to this file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<Vec<String>>,
+    #[serde(default)]
+    pub rulesets: Vec<RulesetConfig>,
+    #[serde(
+        rename(serialize = "ignore-paths", deserialize = "ignore-paths"),
+        skip_serializing_if = "Option::is_none"
+    )]
     pub ignore_paths: Option<Vec<String>>,
-    #[serde(rename(serialize = "ignore-gitignore", deserialize = "ignore-gitignore"))]
+    #[serde(
+        rename(serialize = "ignore-gitignore", deserialize = "ignore-gitignore"),
+        skip_serializing_if = "Option::is_none"
+    )]
     pub ignore_gitignore: Option<bool>,
-    #[serde(rename(serialize = "max-file-size-kb", deserialize = "max-file-size-kb"))]
+    #[serde(
+        rename(serialize = "max-file-size-kb", deserialize = "max-file-size-kb"),
+        skip_serializing_if = "Option::is_none"
+    )]
     pub max_file_size_kb: Option<u64>,
+    #[serde(
+        rename(
+            serialize = "header-files-language",
+            deserialize = "header-files-language"
+        ),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub header_files_language: Option<HeaderFilesLanguage>,
+    #[serde(
+        rename(serialize = "language-mappings", deserialize = "language-mappings"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub language_mappings: Option<Vec<LanguageMapping>>,
 }
 
 impl fmt::Display for ConfigFile {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let rules_string = self.rulesets.join(",");
+        let rules_string = self
+            .rulesets
+            .iter()
+            .map(|r| r.name())
+            .collect::<Vec<&str>>()
+            .join(",");
         let ignore_path_string = match &self.ignore_paths {
             Some(i) => i.join(","),
             None => "".to_string(),
@@ -33,3 +178,16 @@ impl fmt::Display for ConfigFile {
         )
     }
 }
+
+// The configuration that applies to a directory of the analyzed directory and its
+// subdirectories: the configuration file of the directory merged with the configuration
+// of its parent directories. The paths of the configuration are relative to the analyzed
+// directory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirectoryConfig {
+    // directory relative to the analyzed directory (empty for the analyzed directory)
+    pub directory: String,
+    // path of the configuration file relative to the analyzed directory
+    pub path: String,
+    pub config: ConfigFile,
+}
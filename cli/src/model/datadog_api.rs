@@ -74,8 +74,11 @@ impl ApiResponseRuleset {
                             code_base64: t.code_base64,
                             filename: t.filename,
                             annotation_count: t.annotation_count,
+                            expected_violations: vec![],
+                            fixed_code_base64: None,
                         })
                         .collect(),
+                    signature: None,
                 })
                 .collect(),
             None => Vec::new(),
@@ -84,6 +87,7 @@ impl ApiResponseRuleset {
             rules,
             description: Some(description),
             name: ruleset_name,
+            signature: None,
         }
     }
 }
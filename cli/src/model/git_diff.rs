@@ -0,0 +1,16 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+// the changes of a repository since a base revision
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GitDiff {
+    // commit the changes are compared to (the merge base of the base revision and HEAD)
+    pub base_commit: String,
+    // commit of HEAD
+    pub head_commit: String,
+    // files added or modified since the base commit, relative to the repository
+    pub changed_files: BTreeSet<String>,
+    // files that did not exist in the base commit (renamed files are not new files)
+    pub new_files: BTreeSet<String>,
+    // lines added or modified in each file, as ranges of lines (first and last line)
+    pub changed_lines: BTreeMap<String, Vec<(u32, u32)>>,
+}
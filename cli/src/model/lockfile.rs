@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// a ruleset recorded in the lockfile
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct LockedRuleset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub checksum: String,
+    // checksum of each rule, by rule name
+    pub rules: BTreeMap<String, String>,
+}
+
+// the lockfile of the repository: the rules fetched for each ruleset
+// of the configuration file
+#[derive(Clone, Default, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct Lockfile {
+    pub rulesets: BTreeMap<String, LockedRuleset>,
+}
@@ -0,0 +1,48 @@
+use kernel::model::common::Language;
+use kernel::model::rule::{EntityChecked, RuleCategory, RuleSeverity, RuleType};
+use kernel::model::rule_test::ExpectedViolation;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+fn default_rule_type() -> RuleType {
+    RuleType::TreeSitterQuery
+}
+
+// the ruleset.yaml file at the root of a ruleset directory
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct RulesetMetadata {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+// the metadata of a test in the rule.yaml file. The code of the test is in the tests
+// directory of the rule.
+#[derive(Clone, Deserialize, Debug, Serialize, Default)]
+pub struct RuleTestMetadata {
+    #[serde(default)]
+    pub annotation_count: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected_violations: Vec<ExpectedViolation>,
+}
+
+// the rule.yaml file of a rule directory
+#[derive(Clone, Deserialize, Debug, Serialize)]
+pub struct RuleMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub short_description: Option<String>,
+    pub category: RuleCategory,
+    pub severity: RuleSeverity,
+    pub language: Language,
+    #[serde(rename = "type", default = "default_rule_type")]
+    pub rule_type: RuleType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity_checked: Option<EntityChecked>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    // metadata of the tests, by filename
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tests: BTreeMap<String, RuleTestMetadata>,
+}
@@ -0,0 +1,208 @@
+use glob_match::glob_match;
+use kernel::model::rule::{Rule, RuleInternal, RuleResult, RuleSeverity};
+use std::path::Path;
+
+use crate::model::config_file::{DirectoryConfig, RuleConfig, RulesetConfig};
+
+// a path matches a pattern if it matches the glob or if it is under the directory
+// (same as the ignore-paths of the configuration file)
+fn path_matches(pattern: &str, path: &str) -> bool {
+    glob_match(pattern, path) || Path::new(path).starts_with(Path::new(pattern))
+}
+
+impl RuleConfig {
+    /// Indicates if the rule is executed on a path, relative to the analyzed directory.
+    pub fn applies_to(&self, path: &str) -> bool {
+        let included = self
+            .only
+            .as_ref()
+            .is_none_or(|only| only.iter().any(|p| path_matches(p, path)));
+        included && !self.ignore.iter().any(|p| path_matches(p, path))
+    }
+}
+
+/// Configuration of a rule, from the configuration of its ruleset and the configuration
+/// of the rule itself. The configuration of the rule takes precedence for the `enabled`
+/// and `severity` options and a path must match the paths of both configurations.
+#[derive(Clone, Debug, Default)]
+pub struct EffectiveRuleConfig<'a> {
+    pub ruleset: Option<&'a RuleConfig>,
+    pub rule: Option<&'a RuleConfig>,
+}
+
+impl EffectiveRuleConfig<'_> {
+    pub fn is_enabled(&self) -> bool {
+        self.rule
+            .and_then(|c| c.enabled)
+            .or(self.ruleset.and_then(|c| c.enabled))
+            .unwrap_or(true)
+    }
+
+    pub fn severity(&self) -> Option<RuleSeverity> {
+        self.rule
+            .and_then(|c| c.severity)
+            .or(self.ruleset.and_then(|c| c.severity))
+    }
+
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.ruleset.is_none_or(|c| c.applies_to(path))
+            && self.rule.is_none_or(|c| c.applies_to(path))
+    }
+}
+
+/// Get the configuration of a rule from its full name (e.g. `python-security/no-eval`).
+pub fn get_rule_config<'a>(
+    rulesets_config: &'a [RulesetConfig],
+    rule_name: &str,
+) -> EffectiveRuleConfig<'a> {
+    rule_name
+        .split_once('/')
+        .and_then(|(ruleset_name, short_name)| {
+            rulesets_config
+                .iter()
+                .find(|r| r.name() == ruleset_name)
+                .map(|r| EffectiveRuleConfig {
+                    ruleset: r.config(),
+                    rule: r.rule_config(short_name),
+                })
+        })
+        .unwrap_or_default()
+}
+
+/// Indicates if a rule is executed with a configuration: the configuration uses the ruleset
+/// of the rule and the rule is not disabled.
+pub fn is_rule_enabled(rulesets_config: &[RulesetConfig], rule_name: &str) -> bool {
+    rule_name
+        .split_once('/')
+        .is_some_and(|(ruleset_name, _)| rulesets_config.iter().any(|r| r.name() == ruleset_name))
+        && get_rule_config(rulesets_config, rule_name).is_enabled()
+}
+
+/// Remove the rules that are not enabled in any of the configurations and set the severity
+/// of the rules with a severity override in the configuration of the analyzed directory
+/// (the first configuration).
+pub fn apply_rules_config(rules: Vec<Rule>, directory_configs: &[DirectoryConfig]) -> Vec<Rule> {
+    rules
+        .into_iter()
+        .filter(|r| {
+            directory_configs
+                .iter()
+                .any(|c| is_rule_enabled(&c.config.rulesets, &r.name))
+        })
+        .map(|mut r| {
+            if let Some(severity) = directory_configs
+                .first()
+                .and_then(|c| get_rule_config(&c.config.rulesets, &r.name).severity())
+            {
+                r.severity = severity;
+            }
+            r
+        })
+        .collect()
+}
+
+/// Get the rules to execute on a path, relative to the analyzed directory, with the
+/// configuration that applies to the path.
+pub fn get_rules_for_path(
+    rules: &[RuleInternal],
+    rulesets_config: &[RulesetConfig],
+    path: &str,
+) -> Vec<RuleInternal> {
+    rules
+        .iter()
+        .filter(|r| {
+            is_rule_enabled(rulesets_config, &r.name)
+                && get_rule_config(rulesets_config, &r.name).applies_to(path)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Set the severity of the violations of the rules with a severity override.
+pub fn apply_severity_overrides(
+    rule_results: &mut [RuleResult],
+    rulesets_config: &[RulesetConfig],
+) {
+    for rule_result in rule_results {
+        if let Some(severity) = get_rule_config(rulesets_config, &rule_result.rule_name).severity()
+        {
+            for violation in rule_result.violations.iter_mut() {
+                violation.severity = severity;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config_file::DetailedRulesetConfig;
+    use std::collections::BTreeMap;
+
+    fn rulesets_config() -> Vec<RulesetConfig> {
+        vec![
+            RulesetConfig::Name("python-best-practices".to_string()),
+            RulesetConfig::Detailed(DetailedRulesetConfig {
+                name: "python-security".to_string(),
+                config: RuleConfig {
+                    only: Some(vec!["src".to_string(), "scripts/**".to_string()]),
+                    ..Default::default()
+                },
+                rules: BTreeMap::from([
+                    (
+                        "subprocess-shell-true".to_string(),
+                        RuleConfig {
+                            ignore: vec!["scripts/**".to_string()],
+                            severity: Some(RuleSeverity::Notice),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "no-eval".to_string(),
+                        RuleConfig {
+                            enabled: Some(false),
+                            ..Default::default()
+                        },
+                    ),
+                ]),
+                ..Default::default()
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_get_rule_config() {
+        let rulesets_config = rulesets_config();
+
+        let config = get_rule_config(&rulesets_config, "python-security/subprocess-shell-true");
+        assert!(config.is_enabled());
+        assert_eq!(Some(RuleSeverity::Notice), config.severity());
+        assert!(config.applies_to("src/main.py"));
+        assert!(!config.applies_to("scripts/deploy.py"));
+        assert!(!config.applies_to("tests/test_main.py"));
+
+        let config = get_rule_config(&rulesets_config, "python-security/other-rule");
+        assert!(config.is_enabled());
+        assert_eq!(None, config.severity());
+        assert!(config.applies_to("scripts/deploy.py"));
+        assert!(!config.applies_to("tests/test_main.py"));
+
+        assert!(!get_rule_config(&rulesets_config, "python-security/no-eval").is_enabled());
+
+        let config = get_rule_config(&rulesets_config, "python-best-practices/rule");
+        assert!(config.is_enabled());
+        assert!(config.applies_to("tests/test_main.py"));
+        assert!(get_rule_config(&rulesets_config, "unknown/rule").is_enabled());
+
+        // only the rules of the rulesets of the configuration are executed
+        assert!(is_rule_enabled(
+            &rulesets_config,
+            "python-security/other-rule"
+        ));
+        assert!(!is_rule_enabled(
+            &rulesets_config,
+            "python-security/no-eval"
+        ));
+        assert!(!is_rule_enabled(&rulesets_config, "unknown/rule"));
+    }
+}
@@ -0,0 +1,324 @@
+use kernel::analysis::javascript::validate_javascript;
+use kernel::analysis::tree_sitter::{check_query, validate_query};
+use kernel::model::rule::{Rule, RuleType};
+use kernel::model::ruleset::RuleSet;
+use kernel::utils::decode_base64_string;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+
+// definition of the visit function called for each match of the query
+const VISIT_FUNCTION_REGEX: &str =
+    r"\bfunction\s+visit\s*\(|\b(?:const|let|var)\s+visit\s*=|\bvisit\s*=\s*(?:function|\()";
+// captures used in the code of a rule (e.g. node.captures["name"] or node.captures.name)
+const CAPTURE_REFERENCE_REGEX: &str = r#"\bcaptures(?:List)?\s*(?:\[\s*["'`](?P<quoted>[^"'`]+)["'`]\s*\]|\.(?P<field>[A-Za-z_$][\w$]*))"#;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LintLevel {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for LintLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a rule by the linter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LintMessage {
+    pub rule: String,
+    pub level: LintLevel,
+    pub message: String,
+}
+
+impl fmt::Display for LintMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.level, self.rule, self.message)
+    }
+}
+
+// collect the messages of a rule
+struct RuleLinter<'a> {
+    rule: &'a Rule,
+    messages: Vec<LintMessage>,
+}
+
+impl RuleLinter<'_> {
+    fn add(&mut self, level: LintLevel, message: String) {
+        self.messages.push(LintMessage {
+            rule: self.rule.name.clone(),
+            level,
+            message,
+        });
+    }
+
+    // decode a base64 field, reporting an error if it is not valid base64 or UTF-8
+    fn decode(&mut self, field: &str, value: &str) -> Option<String> {
+        match decode_base64_string(value.to_string()) {
+            Ok(decoded) => Some(decoded),
+            Err(e) => {
+                self.add(
+                    LintLevel::Error,
+                    format!("field {} cannot be decoded: {}", field, e),
+                );
+                None
+            }
+        }
+    }
+}
+
+// get the names of the captures used in the code of a rule
+fn get_referenced_captures(code: &str) -> BTreeSet<String> {
+    let regex = Regex::new(CAPTURE_REFERENCE_REGEX).expect("valid capture regex");
+    regex
+        .captures_iter(code)
+        .filter_map(|c| c.name("quoted").or_else(|| c.name("field")))
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
+
+/// Lint a rule: check its checksum, that its fields decode to UTF-8, that its query
+/// compiles for its language, that its JavaScript code is valid and defines a `visit`
+/// function and that the captures of the query are used by the code (and vice versa).
+///
+/// Captures starting with `_` are only used in the predicates of the query and are
+/// not reported when the code does not use them.
+pub fn lint_rule(rule: &Rule) -> Vec<LintMessage> {
+    let mut linter = RuleLinter {
+        rule,
+        messages: vec![],
+    };
+
+    if !rule.verify_checksum() {
+        linter.add(
+            LintLevel::Error,
+            format!(
+                "invalid checksum, expected {}, got {}",
+                rule.compute_checksum(),
+                rule.checksum
+            ),
+        );
+    }
+    if rule.rule_type != RuleType::TreeSitterQuery {
+        linter.add(
+            LintLevel::Error,
+            format!("unsupported rule type {:?}", rule.rule_type),
+        );
+    }
+
+    if let Some(short_description) = &rule.short_description_base64 {
+        linter.decode("short_description", short_description);
+    }
+    if let Some(description) = &rule.description_base64 {
+        linter.decode("description", description);
+    }
+    for test in &rule.tests {
+        linter.decode(
+            format!("code of test {}", test.filename).as_str(),
+            &test.code_base64,
+        );
+        if let Some(fixed_code) = &test.fixed_code_base64 {
+            linter.decode(
+                format!("fixed_code of test {}", test.filename).as_str(),
+                fixed_code,
+            );
+        }
+    }
+
+    let code = linter.decode("code", &rule.code_base64);
+    if let Some(code) = &code {
+        if let Err(e) = validate_javascript(code) {
+            linter.add(LintLevel::Error, format!("invalid JavaScript code: {}", e));
+        }
+        let visit_regex = Regex::new(VISIT_FUNCTION_REGEX).expect("valid visit regex");
+        if !visit_regex.is_match(code) {
+            linter.add(LintLevel::Error, "no visit function defined".to_string());
+        }
+    }
+
+    let query = match &rule.tree_sitter_query_base64 {
+        Some(query) => linter.decode("tree_sitter_query", query),
+        None => {
+            linter.add(LintLevel::Error, "no tree-sitter query".to_string());
+            None
+        }
+    };
+    let query_captures = query.as_ref().and_then(|query| {
+        if let Err(e) = validate_query(query, &rule.language) {
+            linter.add(
+                LintLevel::Error,
+                format!("invalid tree-sitter query: {}", e),
+            );
+            return None;
+        }
+        // a query valid for some grammars of the language is only executed on the files
+        // of these grammars (e.g. a TypeScript query with JSX is only used for .tsx files)
+        let mut captures = None;
+        for (grammar, result) in check_query(query, &rule.language) {
+            match result {
+                Ok(q) if captures.is_none() => {
+                    captures = Some(q.capture_names().iter().cloned().collect::<BTreeSet<_>>())
+                }
+                Ok(_) => {}
+                Err(e) => linter.add(
+                    LintLevel::Warning,
+                    format!(
+                        "tree-sitter query is invalid for the grammar {}, the rule is not executed on its files: {}",
+                        grammar, e
+                    ),
+                ),
+            }
+        }
+        captures
+    });
+
+    if let (Some(code), Some(query_captures)) = (&code, &query_captures) {
+        let referenced_captures = get_referenced_captures(code);
+        for capture in referenced_captures.difference(query_captures) {
+            linter.add(
+                LintLevel::Warning,
+                format!(
+                    "capture {} is used in the code but not defined in the query",
+                    capture
+                ),
+            );
+        }
+        for capture in query_captures
+            .difference(&referenced_captures)
+            .filter(|c| !c.starts_with('_'))
+        {
+            linter.add(
+                LintLevel::Warning,
+                format!(
+                    "capture {} is defined in the query but not used in the code",
+                    capture
+                ),
+            );
+        }
+    }
+
+    linter.messages
+}
+
+/// Lint all the rules of a ruleset.
+pub fn lint_ruleset(ruleset: &RuleSet) -> Vec<LintMessage> {
+    ruleset.rules.iter().flat_map(lint_rule).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::model::common::Language;
+    use kernel::model::rule::{RuleCategory, RuleSeverity};
+    use kernel::utils::encode_base64_string;
+    use std::collections::HashMap;
+
+    fn rule(code: &str, query: &str) -> Rule {
+        let mut rule = Rule {
+            name: "myruleset/myrule".to_string(),
+            short_description_base64: None,
+            description_base64: None,
+            category: RuleCategory::BestPractices,
+            severity: RuleSeverity::Warning,
+            language: Language::Python,
+            rule_type: RuleType::TreeSitterQuery,
+            entity_checked: None,
+            code_base64: encode_base64_string(code.to_string()),
+            checksum: "".to_string(),
+            pattern: None,
+            tree_sitter_query_base64: Some(encode_base64_string(query.to_string())),
+            variables: HashMap::new(),
+            tests: vec![],
+            signature: None,
+        };
+        rule.checksum = rule.compute_checksum();
+        rule
+    }
+
+    fn messages(rule: &Rule) -> Vec<String> {
+        lint_rule(rule).iter().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn test_get_referenced_captures() {
+        let code = r#"
+const name = node.captures["name"];
+const params = node.captures.params;
+const args = node.capturesList['args'];
+"#;
+        assert_eq!(
+            vec!["args", "name", "params"],
+            get_referenced_captures(code)
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_lint_valid_rule() {
+        let code = r#"
+function visit(node, filename, code) {
+    const functionName = node.captures["name"];
+}
+"#;
+        let query = r#"((function_definition name: (identifier) @name body: (_) @_body) (#match? @_body "pass"))"#;
+        assert!(lint_rule(&rule(code, query)).is_empty());
+    }
+
+    #[test]
+    fn test_lint_invalid_rule() {
+        let mut invalid = rule(
+            "function visit(node, filename, code) {\n  node.captures.missing;\n",
+            "(function_definition name: (identifier) @name)",
+        );
+        invalid.description_base64 = Some(encode_base64_string("desc".to_string()) + "!");
+        invalid.checksum = "foo".to_string();
+        let messages = messages(&invalid);
+        assert_eq!(5, messages.len());
+        assert!(messages[0].starts_with("error: myruleset/myrule: invalid checksum"));
+        assert!(
+            messages[1].starts_with("error: myruleset/myrule: field description cannot be decoded")
+        );
+        assert!(messages[2].starts_with("error: myruleset/myrule: invalid JavaScript code: "));
+        assert_eq!(
+            "warning: myruleset/myrule: capture missing is used in the code but not defined in the query",
+            messages[3]
+        );
+        assert_eq!(
+            "warning: myruleset/myrule: capture name is defined in the query but not used in the code",
+            messages[4]
+        );
+
+        let messages = messages(&rule(
+            "const foo = 1;",
+            "(function_definition name: (foo) @name)",
+        ));
+        assert_eq!(2, messages.len());
+        assert_eq!(
+            "error: myruleset/myrule: no visit function defined",
+            messages[0]
+        );
+        assert!(messages[1].starts_with("error: myruleset/myrule: invalid tree-sitter query: "));
+    }
+
+    // a TypeScript query with JSX is only valid for the tsx grammar
+    #[test]
+    fn test_lint_jsx_rule() {
+        let mut jsx_rule = rule(
+            "function visit(node, filename, code) {\n  node.captures.jsx;\n}\n",
+            "(jsx_element) @jsx",
+        );
+        jsx_rule.language = Language::TypeScript;
+        jsx_rule.checksum = jsx_rule.compute_checksum();
+        let messages = messages(&jsx_rule);
+        assert_eq!(1, messages.len());
+        assert!(messages[0].starts_with(
+            "warning: myruleset/myrule: tree-sitter query is invalid for the grammar typescript"
+        ));
+    }
+}
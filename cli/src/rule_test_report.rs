@@ -0,0 +1,177 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Result of one test of a rule, used to build the reports of the test runner.
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct RuleTestResult {
+    pub ruleset: String,
+    pub rule: String,
+    pub filename: String,
+    // error when the test fails
+    pub error: Option<String>,
+    pub snapshot_updated: bool,
+    pub execution_time_ms: u128,
+}
+
+impl RuleTestResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    tests: usize,
+    failures: usize,
+    results: &'a [RuleTestResult],
+}
+
+fn count_failures(results: &[RuleTestResult]) -> usize {
+    results.iter().filter(|r| !r.is_success()).count()
+}
+
+fn get_time_in_seconds(results: &[RuleTestResult]) -> f64 {
+    results
+        .iter()
+        .map(|r| r.execution_time_ms as f64 / 1000.0)
+        .sum()
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Generate a JSON report with the number of tests, the number of failures and all results.
+pub fn generate_json_report(results: &[RuleTestResult]) -> Result<String> {
+    Ok(serde_json::to_string(&JsonReport {
+        tests: results.len(),
+        failures: count_failures(results),
+        results,
+    })?)
+}
+
+/// Generate a JUnit XML report with one test suite per ruleset and one test case per test.
+/// Results of the same ruleset must be consecutive.
+pub fn generate_junit_report(results: &[RuleTestResult]) -> Result<String> {
+    let mut report = String::new();
+    writeln!(report, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        report,
+        r#"<testsuites name="rules" tests="{}" failures="{}" time="{:.3}">"#,
+        results.len(),
+        count_failures(results),
+        get_time_in_seconds(results)
+    )?;
+    let mut remaining = results;
+    while let Some(first) = remaining.first() {
+        let suite_length = remaining
+            .iter()
+            .position(|r| r.ruleset != first.ruleset)
+            .unwrap_or(remaining.len());
+        let (suite, rest) = remaining.split_at(suite_length);
+        writeln!(
+            report,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" time="{:.3}">"#,
+            escape_xml(first.ruleset.as_str()),
+            suite.len(),
+            count_failures(suite),
+            get_time_in_seconds(suite)
+        )?;
+        for result in suite {
+            write!(
+                report,
+                r#"    <testcase classname="{}" name="{}" time="{:.3}""#,
+                escape_xml(result.rule.as_str()),
+                escape_xml(result.filename.as_str()),
+                result.execution_time_ms as f64 / 1000.0
+            )?;
+            match &result.error {
+                Some(error) => {
+                    writeln!(report, ">")?;
+                    writeln!(
+                        report,
+                        r#"      <failure message="{}">{}</failure>"#,
+                        escape_xml(error.lines().next().unwrap_or_default()),
+                        escape_xml(error.as_str())
+                    )?;
+                    writeln!(report, "    </testcase>")?;
+                }
+                None => writeln!(report, "/>")?,
+            }
+        }
+        writeln!(report, "  </testsuite>")?;
+        remaining = rest;
+    }
+    writeln!(report, "</testsuites>")?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results() -> Vec<RuleTestResult> {
+        vec![
+            RuleTestResult {
+                ruleset: "python-security".to_string(),
+                rule: "python-security/no-eval".to_string(),
+                filename: "ok.py".to_string(),
+                error: None,
+                snapshot_updated: false,
+                execution_time_ms: 12,
+            },
+            RuleTestResult {
+                ruleset: "python-security".to_string(),
+                rule: "python-security/no-eval".to_string(),
+                filename: "ko.py".to_string(),
+                error: Some("expected 1 annotations, got 0\n<details>".to_string()),
+                snapshot_updated: false,
+                execution_time_ms: 8,
+            },
+            RuleTestResult {
+                ruleset: "python-style".to_string(),
+                rule: "python-style/name".to_string(),
+                filename: "ok.py".to_string(),
+                error: None,
+                snapshot_updated: true,
+                execution_time_ms: 1500,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_generate_junit_report() {
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="rules" tests="3" failures="1" time="1.520">
+  <testsuite name="python-security" tests="2" failures="1" time="0.020">
+    <testcase classname="python-security/no-eval" name="ok.py" time="0.012"/>
+    <testcase classname="python-security/no-eval" name="ko.py" time="0.008">
+      <failure message="expected 1 annotations, got 0">expected 1 annotations, got 0
+&lt;details&gt;</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="python-style" tests="1" failures="0" time="1.500">
+    <testcase classname="python-style/name" name="ok.py" time="1.500"/>
+  </testsuite>
+</testsuites>
+"#;
+        assert_eq!(expected, generate_junit_report(&results()).unwrap());
+    }
+
+    #[test]
+    fn test_generate_json_report() {
+        let report: serde_json::Value =
+            serde_json::from_str(generate_json_report(&results()).unwrap().as_str()).unwrap();
+        assert_eq!(3, report["tests"]);
+        assert_eq!(1, report["failures"]);
+        assert_eq!("ko.py", report["results"][1]["filename"]);
+        assert!(report["results"][0]["error"].is_null());
+        assert_eq!(true, report["results"][2]["snapshot_updated"]);
+    }
+}
@@ -0,0 +1,561 @@
+use crate::fix_utils::apply_fixes;
+use crate::rule_test_report::RuleTestResult;
+use anyhow::{Context, Error, Result};
+use glob_match::glob_match;
+use kernel::analysis::analyze::analyze;
+use kernel::model::analysis::AnalysisOptions;
+use kernel::model::common::Position;
+use kernel::model::rule::Rule;
+use kernel::model::rule_test::{ExpectedViolation, RuleTest};
+use kernel::model::violation::Violation;
+use kernel::utils::{decode_base64_string, encode_base64_string};
+use regex::Regex;
+use std::fmt;
+use std::time::Instant;
+
+// Inline marker in the code of a test: a comment `expect` or `expect: <message regex>`
+// at the end of the line where a violation must start (e.g. `foo()  # expect: invalid`).
+const EXPECT_MARKER_REGEX: &str = r"(?:#|//|/\*)\s*expect(?::\s*(?P<message>.*?))?\s*(?:\*/)?\s*$";
+
+/// A violation expected by a test, from an inline marker or from the expected
+/// violations of the test. Inline markers only specify the line of the violation.
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    pub line: u32,
+    pub col: Option<u32>,
+    pub end: Option<Position>,
+    pub message: Option<Regex>,
+}
+
+impl Expectation {
+    fn matches(&self, violation: &Violation) -> bool {
+        violation.start.line == self.line
+            && self.col.map_or(true, |col| violation.start.col == col)
+            && self.end.as_ref().map_or(true, |end| violation.end == *end)
+            && self
+                .message
+                .as_ref()
+                .map_or(true, |m| m.is_match(violation.message.as_str()))
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}", self.line)?;
+        if let Some(col) = self.col {
+            write!(f, ", col {}", col)?;
+        }
+        if let Some(end) = &self.end {
+            write!(f, ", end {}:{}", end.line, end.col)?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, ", message /{}/", message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Difference between the violations expected by a test and the violations reported
+/// by the rule.
+#[derive(Debug, Default)]
+pub struct ViolationsDiff {
+    pub missing: Vec<Expectation>,
+    pub unexpected: Vec<Violation>,
+}
+
+impl ViolationsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+impl fmt::Display for ViolationsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.missing.is_empty() {
+            writeln!(f, "expected violations not reported:")?;
+            for expectation in &self.missing {
+                writeln!(f, "  - {}", expectation)?;
+            }
+        }
+        if !self.unexpected.is_empty() {
+            writeln!(f, "unexpected violations:")?;
+            for violation in &self.unexpected {
+                writeln!(
+                    f,
+                    "  - {}:{}-{}:{} {}",
+                    violation.start.line,
+                    violation.start.col,
+                    violation.end.line,
+                    violation.end.col,
+                    violation.message
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn compile_message_regex(message: &str) -> Result<Regex> {
+    Regex::new(message).with_context(|| format!("invalid message regex {}", message))
+}
+
+/// Get the expectations from the inline markers of the code of a test.
+pub fn get_inline_expectations(code: &str) -> Result<Vec<Expectation>> {
+    let marker = Regex::new(EXPECT_MARKER_REGEX)?;
+    code.lines()
+        .enumerate()
+        .filter_map(|(index, line)| marker.captures(line).map(|c| (index, c)))
+        .map(|(index, captures)| {
+            let message = captures
+                .name("message")
+                .map(|m| m.as_str())
+                .filter(|m| !m.is_empty())
+                .map(compile_message_regex)
+                .transpose()?;
+            Ok(Expectation {
+                line: index as u32 + 1,
+                col: None,
+                end: None,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn get_expectation(expected_violation: &ExpectedViolation) -> Result<Expectation> {
+    Ok(Expectation {
+        line: expected_violation.start.line,
+        col: Some(expected_violation.start.col),
+        end: expected_violation.end.clone(),
+        message: expected_violation
+            .message
+            .as_deref()
+            .map(compile_message_regex)
+            .transpose()?,
+    })
+}
+
+/// Get all the expectations of a test. The expected violations of the test come first
+/// so that they are matched before the (less precise) inline markers.
+pub fn get_test_expectations(test: &RuleTest, code: &str) -> Result<Vec<Expectation>> {
+    let mut expectations = test
+        .expected_violations
+        .iter()
+        .map(get_expectation)
+        .collect::<Result<Vec<_>>>()?;
+    expectations.extend(get_inline_expectations(code)?);
+    Ok(expectations)
+}
+
+/// Match each expectation with a reported violation. Expectations without violation
+/// are missing and violations without expectation are unexpected.
+pub fn diff_violations(expectations: &[Expectation], violations: &[Violation]) -> ViolationsDiff {
+    let mut unmatched: Vec<&Violation> = violations.iter().collect();
+    let mut missing = vec![];
+    for expectation in expectations {
+        match unmatched.iter().position(|v| expectation.matches(v)) {
+            Some(index) => {
+                unmatched.remove(index);
+            }
+            None => missing.push(expectation.clone()),
+        }
+    }
+    ViolationsDiff {
+        missing,
+        unexpected: unmatched.into_iter().cloned().collect(),
+    }
+}
+
+/// Options to run the tests of a rule.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RuleTestOptions {
+    // rewrite the expected fixed code of the tests instead of comparing it
+    pub update_snapshots: bool,
+}
+
+// get the first line that differs between the expected and actual code
+// (line number starting at 1, expected line, actual line).
+fn get_first_different_line<'a>(
+    expected: &'a str,
+    actual: &'a str,
+) -> Option<(usize, &'a str, &'a str)> {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => return None,
+            (e, a) if e != a => {
+                return Some((
+                    line,
+                    e.unwrap_or("<end of code>"),
+                    a.unwrap_or("<end of code>"),
+                ))
+            }
+            _ => line += 1,
+        }
+    }
+}
+
+// Compare the code with the fixes applied with the snapshot of the test. When updating
+// the snapshots, the snapshot is created if the fixes change the code.
+fn check_fixed_code(
+    test: &mut RuleTest,
+    code: &str,
+    violations: &[Violation],
+    options: &RuleTestOptions,
+) -> Result<()> {
+    if test.fixed_code_base64.is_none() && !options.update_snapshots {
+        return Ok(());
+    }
+    let fixed_code = apply_fixes(code, violations)
+        .with_context(|| format!("error applying the fixes of test {}", test.filename))?;
+
+    if options.update_snapshots {
+        if test.fixed_code_base64.is_some() || fixed_code != code {
+            test.fixed_code_base64 = Some(encode_base64_string(fixed_code));
+        }
+        return Ok(());
+    }
+
+    let expected = decode_base64_string(test.fixed_code_base64.clone().unwrap_or_default())?;
+    if expected == fixed_code {
+        return Ok(());
+    }
+    let (line, expected_line, actual_line) = get_first_different_line(
+        expected.as_str(),
+        fixed_code.as_str(),
+    )
+    .unwrap_or((1, expected.as_str(), fixed_code.as_str()));
+    Err(Error::msg(format!(
+        "error evaluating test {}, fixed code does not match the snapshot at line {}\n  expected: {}\n  actual:   {}",
+        test.filename, line, expected_line, actual_line
+    )))
+}
+
+/// Run a test of a rule. When the test has expectations (inline markers or expected
+/// violations), the violations are compared by position and message. Otherwise, we
+/// only check the number of violations. When the test has a snapshot of the fixed code,
+/// the fixes of the violations are applied and compared with it.
+pub fn test_rule(rule: &Rule, test: &mut RuleTest, options: &RuleTestOptions) -> Result<String> {
+    let rule_internal = rule.to_rule_internal()?;
+    let code = decode_base64_string(test.code_base64.to_string())?;
+    let expectations = get_test_expectations(test, code.as_str())?;
+    let analysis_options = AnalysisOptions {
+        log_output: true,
+        use_debug: true,
+    };
+    let rules = vec![rule_internal];
+    let analyze_result = analyze(
+        &rule.language,
+        rules,
+        test.filename.as_str(),
+        code.as_str(),
+        &analysis_options,
+    );
+
+    let first_results = analyze_result
+        .get(0)
+        .ok_or_else(|| Error::msg("no violation result"))?;
+
+    if !expectations.is_empty() {
+        let diff = diff_violations(&expectations, &first_results.violations);
+        if !diff.is_empty() {
+            return Err(Error::msg(format!(
+                "error evaluating test {}, violations do not match expectations\n{}",
+                test.filename, diff
+            )));
+        }
+    } else if first_results.violations.len() != test.annotation_count as usize {
+        let error =
+            format!(
+            "error evaluating test {}, expected {} annotations, got {}, execution error: {}, output: {}, rule errors: {}",
+            test.filename,
+            test.annotation_count,
+            first_results.violations.len(),
+            first_results.execution_error.clone().unwrap_or("none".to_string()),
+            first_results.output.clone().unwrap_or("none".to_string()),
+            first_results.errors.join(",")
+        );
+        return Err(Error::msg(error));
+    }
+
+    check_fixed_code(test, code.as_str(), &first_results.violations, options)?;
+    Ok("test pass".to_string())
+}
+
+/// Check if a rule is selected by the filters of the test runner: glob patterns matched
+/// against the name of the rule (e.g. `python-security/*`). No filter selects all rules.
+pub fn is_rule_selected(rule_name: &str, filters: &[String]) -> bool {
+    filters.is_empty() || filters.iter().any(|f| glob_match(f.as_str(), rule_name))
+}
+
+/// Run all the tests of a rule. The tests are updated when updating the snapshots.
+pub fn run_rule_tests(
+    ruleset_name: &str,
+    rule: &mut Rule,
+    options: &RuleTestOptions,
+) -> Vec<RuleTestResult> {
+    let rule_copy = rule.clone();
+    rule.tests
+        .iter_mut()
+        .map(|test| {
+            let snapshot = test.fixed_code_base64.clone();
+            let start = Instant::now();
+            let error = test_rule(&rule_copy, test, options)
+                .err()
+                .map(|e| e.to_string());
+            RuleTestResult {
+                ruleset: ruleset_name.to_string(),
+                rule: rule_copy.name.clone(),
+                filename: test.filename.clone(),
+                error,
+                snapshot_updated: test.fixed_code_base64 != snapshot,
+                execution_time_ms: start.elapsed().as_millis(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kernel::model::common::Language;
+    use kernel::model::rule::{RuleCategory, RuleSeverity, RuleType};
+    use std::collections::HashMap;
+
+    fn violation(start: (u32, u32), end: (u32, u32), message: &str) -> Violation {
+        Violation {
+            start: Position {
+                line: start.0,
+                col: start.1,
+            },
+            end: Position {
+                line: end.0,
+                col: end.1,
+            },
+            message: message.to_string(),
+            severity: RuleSeverity::Error,
+            category: RuleCategory::BestPractices,
+            fixes: vec![],
+            fingerprint: None,
+        }
+    }
+
+    fn python_rule() -> Rule {
+        let code = r#"
+function visit(node, filename, code) {
+    const functionName = node.captures["name"];
+    if(functionName && getCodeForNode(functionName, code) !== "bar") {
+        addError(buildError(functionName.start.line, functionName.start.col, functionName.end.line, functionName.end.col,
+                            "invalid name", "CRITICAL", "security"));
+    }
+}
+"#;
+        let query = r#"
+(function_definition
+    name: (identifier) @name
+)
+"#;
+        Rule {
+            name: "myruleset/myrule".to_string(),
+            short_description_base64: None,
+            description_base64: None,
+            category: RuleCategory::BestPractices,
+            severity: RuleSeverity::Warning,
+            language: Language::Python,
+            rule_type: RuleType::TreeSitterQuery,
+            entity_checked: None,
+            code_base64: encode_base64_string(code.to_string()),
+            checksum: "".to_string(),
+            pattern: None,
+            tree_sitter_query_base64: Some(encode_base64_string(query.to_string())),
+            variables: HashMap::new(),
+            tests: vec![],
+            signature: None,
+        }
+    }
+
+    fn python_test(code: &str, annotation_count: u32) -> RuleTest {
+        RuleTest {
+            annotation_count,
+            filename: "myfile.py".to_string(),
+            code_base64: encode_base64_string(code.to_string()),
+            expected_violations: vec![],
+            fixed_code_base64: None,
+        }
+    }
+
+    #[test]
+    fn test_get_inline_expectations() {
+        let code = r#"def foo():  # expect: invalid name
+    pass
+foo()  # expected to be fine
+bar() // expect
+baz() /* expect: some.*thing */
+"#;
+        let expectations = get_inline_expectations(code).unwrap();
+        assert_eq!(3, expectations.len());
+        assert_eq!(1, expectations[0].line);
+        assert_eq!(
+            "invalid name",
+            expectations[0].message.as_ref().unwrap().as_str()
+        );
+        assert_eq!(4, expectations[1].line);
+        assert!(expectations[1].message.is_none());
+        assert_eq!(5, expectations[2].line);
+        assert_eq!(
+            "some.*thing",
+            expectations[2].message.as_ref().unwrap().as_str()
+        );
+        assert!(get_inline_expectations("foo() # expect: (invalid").is_err());
+    }
+
+    #[test]
+    fn test_diff_violations() {
+        let expectations = vec![
+            get_expectation(&ExpectedViolation {
+                start: Position { line: 2, col: 5 },
+                end: Some(Position { line: 2, col: 8 }),
+                message: Some("^invalid".to_string()),
+            })
+            .unwrap(),
+            Expectation {
+                line: 4,
+                col: None,
+                end: None,
+                message: None,
+            },
+            Expectation {
+                line: 6,
+                col: None,
+                end: None,
+                message: Some(Regex::new("name").unwrap()),
+            },
+        ];
+        let violations = vec![
+            violation((2, 5), (2, 8), "invalid name"),
+            violation((4, 1), (4, 3), "something"),
+            violation((6, 1), (6, 3), "wrong message"),
+        ];
+        let diff = diff_violations(&expectations, &violations);
+        assert!(!diff.is_empty());
+        assert_eq!(1, diff.missing.len());
+        assert_eq!(6, diff.missing[0].line);
+        assert_eq!(1, diff.unexpected.len());
+        assert_eq!("wrong message", diff.unexpected[0].message);
+        assert_eq!(
+            "expected violations not reported:\n  - line 6, message /name/\nunexpected violations:\n  - 6:1-6:3 wrong message\n",
+            diff.to_string()
+        );
+
+        assert!(diff_violations(&expectations[0..2], &violations[0..2]).is_empty());
+    }
+
+    // without expectations, we only compare the number of violations
+    #[test]
+    fn test_test_rule_annotation_count() {
+        let rule = python_rule();
+        let code = "def foo():\n    pass\n";
+        let options = RuleTestOptions::default();
+        assert!(test_rule(&rule, &mut python_test(code, 1), &options).is_ok());
+        assert!(test_rule(&rule, &mut python_test(code, 2), &options).is_err());
+    }
+
+    // a violation on the wrong line fails the test even if the count is correct
+    #[test]
+    fn test_test_rule_inline_expectations() {
+        let rule = python_rule();
+        let valid = "def foo():  # expect: invalid name\n    pass\ndef bar():\n    pass\n";
+        let options = RuleTestOptions::default();
+        assert!(test_rule(&rule, &mut python_test(valid, 1), &options).is_ok());
+
+        let invalid = "def foo():\n    pass\ndef bar():  # expect: invalid name\n    pass\n";
+        let error = test_rule(&rule, &mut python_test(invalid, 1), &options).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("expected violations not reported:\n  - line 3, message /invalid name/"));
+        assert!(error
+            .to_string()
+            .contains("unexpected violations:\n  - 1:5-1:8 invalid name"));
+    }
+
+    #[test]
+    fn test_test_rule_fixed_code() {
+        let mut rule = python_rule();
+        let code = r#"
+function visit(node, filename, code) {
+    const functionName = node.captures["name"];
+    if(functionName && getCodeForNode(functionName, code) !== "bar") {
+        const error = buildError(functionName.start.line, functionName.start.col, functionName.end.line, functionName.end.col,
+                                 "invalid name", "CRITICAL", "security");
+        const edit = buildEdit(functionName.start.line, functionName.start.col, functionName.end.line, functionName.end.col, "update", "bar");
+        addError(error.addFix(buildFix("use bar", [edit])));
+    }
+}
+"#;
+        rule.code_base64 = encode_base64_string(code.to_string());
+        let code = "def foo():\n    pass\n";
+
+        // no snapshot: the fixes are not checked
+        let mut test = python_test(code, 1);
+        assert!(test_rule(&rule, &mut test, &RuleTestOptions::default()).is_ok());
+
+        // the snapshot is created when updating the snapshots
+        let update = RuleTestOptions {
+            update_snapshots: true,
+        };
+        assert!(test_rule(&rule, &mut test, &update).is_ok());
+        assert_eq!(
+            Some(encode_base64_string("def bar():\n    pass\n".to_string())),
+            test.fixed_code_base64
+        );
+        assert!(test_rule(&rule, &mut test, &RuleTestOptions::default()).is_ok());
+
+        // a different snapshot fails the test
+        test.fixed_code_base64 = Some(encode_base64_string("def baz():\n    pass\n".to_string()));
+        let error = test_rule(&rule, &mut test, &RuleTestOptions::default()).unwrap_err();
+        assert_eq!(
+            "error evaluating test myfile.py, fixed code does not match the snapshot at line 1\n  expected: def baz():\n  actual:   def bar():",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_get_first_different_line() {
+        assert_eq!(None, get_first_different_line("a\nb\n", "a\nb\n"));
+        assert_eq!(
+            Some((2, "b", "c")),
+            get_first_different_line("a\nb\n", "a\nc\n")
+        );
+        assert_eq!(
+            Some((2, "<end of code>", "b")),
+            get_first_different_line("a\n", "a\nb\n")
+        );
+    }
+
+    #[test]
+    fn test_is_rule_selected() {
+        assert!(is_rule_selected("python-security/no-eval", &[]));
+        let filters = vec!["python-security/*".to_string(), "*/no-print".to_string()];
+        assert!(is_rule_selected("python-security/no-eval", &filters));
+        assert!(is_rule_selected("python-style/no-print", &filters));
+        assert!(!is_rule_selected("python-style/no-eval", &filters));
+    }
+
+    #[test]
+    fn test_run_rule_tests() {
+        let mut rule = python_rule();
+        rule.tests = vec![
+            python_test("def foo():\n    pass\n", 1),
+            python_test("def bar():\n    pass\n", 1),
+        ];
+        let results = run_rule_tests("myruleset", &mut rule, &RuleTestOptions::default());
+        assert_eq!(2, results.len());
+        assert!(results[0].is_success());
+        assert_eq!("myruleset", results[0].ruleset);
+        assert_eq!("myruleset/myrule", results[0].rule);
+        assert!(!results[1].is_success());
+        assert!(!results[1].snapshot_updated);
+    }
+}
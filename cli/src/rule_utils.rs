@@ -1,9 +1,28 @@
-use anyhow::Result;
+use crate::model::rule_directory::{RuleMetadata, RuleTestMetadata, RulesetMetadata};
+use anyhow::{anyhow, Context, Result};
 use kernel::model::common::Language;
 use kernel::model::rule::Rule;
+use kernel::model::rule_test::RuleTest;
 use kernel::model::ruleset::RuleSet;
+use kernel::utils::{decode_base64_string, encode_base64_string};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::{fs::File, io::BufReader};
 
+// extension of the rulesets files in a rulesets directory
+const RULESETS_FILE_EXTENSION: &str = "json";
+
+// files of a ruleset directory: the ruleset.yaml file and one directory per rule.
+const RULESET_METADATA_FILE: &str = "ruleset.yaml";
+const RULE_METADATA_FILE: &str = "rule.yaml";
+const RULE_QUERY_FILE: &str = "query.scm";
+const RULE_CODE_FILE: &str = "visit.js";
+const RULE_DESCRIPTION_FILE: &str = "description.md";
+const RULE_TESTS_DIRECTORY: &str = "tests";
+// extension of the snapshot of the fixed code of a test (e.g. tests/foo.py.fixed)
+const FIXED_CODE_EXTENSION: &str = "fixed";
+
 pub fn get_rulesets_from_file(file_path: &str) -> Result<Vec<RuleSet>> {
     let file = File::open(file_path)?;
     let reader = BufReader::new(file);
@@ -14,6 +33,333 @@ pub fn get_rulesets_from_reader<R: std::io::Read>(reader: R) -> Result<Vec<RuleS
     Ok(serde_json::from_reader(reader)?)
 }
 
+/// Get the rulesets files of a directory: all the JSON files (with the same format as
+/// `get_rulesets_from_file`) at the root of the directory, sorted by name.
+pub fn get_rulesets_files_from_directory(directory: &str) -> Result<Vec<String>> {
+    let mut files = fs::read_dir(directory)
+        .with_context(|| format!("cannot read rulesets directory {}", directory))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_file() && p.extension().and_then(|e| e.to_str()) == Some(RULESETS_FILE_EXTENSION)
+        })
+        .filter_map(|p| p.to_str().map(|p| p.to_string()))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+/// Write rulesets in a file, with the format read by `get_rulesets_from_file`.
+pub fn write_rulesets_to_file(file_path: &Path, rulesets: &[RuleSet]) -> Result<()> {
+    let file = File::create(file_path)
+        .with_context(|| format!("cannot create file {}", file_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, rulesets)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Check if a directory is a ruleset directory (i.e. it has a ruleset.yaml file).
+pub fn is_ruleset_directory(directory: &Path) -> bool {
+    directory.join(RULESET_METADATA_FILE).is_file()
+}
+
+/// Get the ruleset directories of a directory: the directory itself if it is a ruleset
+/// directory, its subdirectories that are ruleset directories otherwise.
+pub fn get_rulesets_directories_from_directory(directory: &str) -> Result<Vec<String>> {
+    if is_ruleset_directory(Path::new(directory)) {
+        return Ok(vec![directory.to_string()]);
+    }
+    Ok(get_sorted_entries(Path::new(directory))?
+        .into_iter()
+        .filter(|p| is_ruleset_directory(p))
+        .filter_map(|p| p.to_str().map(|p| p.to_string()))
+        .collect())
+}
+
+fn get_sorted_entries(directory: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(directory)
+        .with_context(|| format!("cannot read directory {}", directory.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect::<Vec<_>>();
+    entries.sort();
+    Ok(entries)
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    fs::read_to_string(path).with_context(|| format!("cannot read file {}", path.display()))
+}
+
+fn read_optional_file(path: &Path) -> Result<Option<String>> {
+    if path.is_file() {
+        read_file(path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).with_context(|| format!("cannot write file {}", path.display()))
+}
+
+fn get_file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.to_string())
+        .ok_or_else(|| anyhow!("invalid file name {}", path.display()))
+}
+
+fn get_fixed_code_path(tests_directory: &Path, filename: &str) -> PathBuf {
+    tests_directory.join(format!("{}.{}", filename, FIXED_CODE_EXTENSION))
+}
+
+// name of a rule in its ruleset (e.g. no-eval for python-security/no-eval)
+fn get_rule_short_name(rule_name: &str) -> &str {
+    rule_name.rsplit('/').next().unwrap_or(rule_name)
+}
+
+/// Check that a name (e.g. of a ruleset) can be used as the name of a file or directory:
+/// it must not be empty, absolute, contain a path separator or `..`, so that it cannot
+/// be used to write outside of the directory where it is joined.
+pub fn check_path_name(kind: &str, name: &str) -> Result<()> {
+    if name.is_empty()
+        || name == "."
+        || name.contains('/')
+        || name.contains('\\')
+        || name.contains("..")
+        || Path::new(name).is_absolute()
+    {
+        return Err(anyhow!("invalid {} name {}", kind, name));
+    }
+    Ok(())
+}
+
+fn get_rule_tests_from_directory(
+    tests_directory: &Path,
+    metadata: &RuleMetadata,
+) -> Result<Vec<RuleTest>> {
+    let mut tests = vec![];
+    if tests_directory.is_dir() {
+        for path in get_sorted_entries(tests_directory)? {
+            if !path.is_file()
+                || path.extension().and_then(|e| e.to_str()) == Some(FIXED_CODE_EXTENSION)
+            {
+                continue;
+            }
+            let filename = get_file_name(&path)?;
+            let test_metadata = metadata.tests.get(&filename).cloned().unwrap_or_default();
+            tests.push(RuleTest {
+                annotation_count: test_metadata.annotation_count,
+                code_base64: encode_base64_string(read_file(&path)?),
+                expected_violations: test_metadata.expected_violations,
+                fixed_code_base64: read_optional_file(&get_fixed_code_path(
+                    tests_directory,
+                    filename.as_str(),
+                ))?
+                .map(encode_base64_string),
+                filename,
+            });
+        }
+    }
+    if let Some(filename) = metadata
+        .tests
+        .keys()
+        .find(|f| !tests.iter().any(|t| &t.filename == *f))
+    {
+        return Err(anyhow!(
+            "test {} not found in {}",
+            filename,
+            tests_directory.display()
+        ));
+    }
+    Ok(tests)
+}
+
+fn get_rule_from_directory(ruleset_name: &str, directory: &Path) -> Result<Rule> {
+    let metadata_path = directory.join(RULE_METADATA_FILE);
+    let metadata: RuleMetadata = serde_yaml::from_str(read_file(&metadata_path)?.as_str())
+        .with_context(|| format!("invalid rule file {}", metadata_path.display()))?;
+    let tests = get_rule_tests_from_directory(&directory.join(RULE_TESTS_DIRECTORY), &metadata)?;
+    let mut rule = Rule {
+        name: format!("{}/{}", ruleset_name, get_file_name(directory)?),
+        short_description_base64: metadata.short_description.map(encode_base64_string),
+        description_base64: read_optional_file(&directory.join(RULE_DESCRIPTION_FILE))?
+            .map(encode_base64_string),
+        category: metadata.category,
+        severity: metadata.severity,
+        language: metadata.language,
+        rule_type: metadata.rule_type,
+        entity_checked: metadata.entity_checked,
+        code_base64: encode_base64_string(read_file(&directory.join(RULE_CODE_FILE))?),
+        checksum: String::new(),
+        pattern: metadata.pattern,
+        tree_sitter_query_base64: read_optional_file(&directory.join(RULE_QUERY_FILE))?
+            .map(encode_base64_string),
+        variables: metadata.variables.into_iter().collect(),
+        tests,
+        signature: None,
+    };
+    rule.checksum = rule.compute_checksum();
+    Ok(rule)
+}
+
+/// Read a ruleset from a ruleset directory:
+///
+/// ```text
+/// python-security/
+///   ruleset.yaml          name and description of the ruleset
+///   no-eval/              one directory per rule, named after the rule
+///     rule.yaml           category, severity, language and metadata of the tests
+///     query.scm           tree-sitter query
+///     visit.js            code of the rule
+///     description.md
+///     tests/
+///       eval.py           code of a test
+///       eval.py.fixed     code of the test once fixed (optional snapshot)
+/// ```
+///
+/// The checksums of the rules are computed from their code.
+pub fn get_ruleset_from_directory(directory: &str) -> Result<RuleSet> {
+    let directory = Path::new(directory);
+    let metadata_path = directory.join(RULESET_METADATA_FILE);
+    let metadata: RulesetMetadata = serde_yaml::from_str(read_file(&metadata_path)?.as_str())
+        .with_context(|| format!("invalid ruleset file {}", metadata_path.display()))?;
+    let rules = get_sorted_entries(directory)?
+        .into_iter()
+        .filter(|p| p.join(RULE_METADATA_FILE).is_file())
+        .map(|p| get_rule_from_directory(metadata.name.as_str(), &p))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(RuleSet {
+        name: metadata.name,
+        description: metadata.description.map(encode_base64_string),
+        rules,
+        signature: None,
+    })
+}
+
+fn decode_optional(value: &Option<String>) -> Result<Option<String>> {
+    value.clone().map(decode_base64_string).transpose()
+}
+
+fn write_rule_to_directory(rule: &Rule, directory: &Path) -> Result<()> {
+    fs::create_dir_all(directory)
+        .with_context(|| format!("cannot create directory {}", directory.display()))?;
+    let metadata = RuleMetadata {
+        short_description: decode_optional(&rule.short_description_base64)?,
+        category: rule.category,
+        severity: rule.severity,
+        language: rule.language,
+        rule_type: rule.rule_type,
+        entity_checked: rule.entity_checked,
+        pattern: rule.pattern.clone(),
+        variables: rule.variables.clone().into_iter().collect(),
+        tests: rule
+            .tests
+            .iter()
+            .map(|t| {
+                (
+                    t.filename.clone(),
+                    RuleTestMetadata {
+                        annotation_count: t.annotation_count,
+                        expected_violations: t.expected_violations.clone(),
+                    },
+                )
+            })
+            .collect(),
+    };
+    write_file(
+        &directory.join(RULE_METADATA_FILE),
+        serde_yaml::to_string(&metadata)?.as_str(),
+    )?;
+    write_file(
+        &directory.join(RULE_CODE_FILE),
+        decode_base64_string(rule.code_base64.clone())?.as_str(),
+    )?;
+    if let Some(query) = decode_optional(&rule.tree_sitter_query_base64)? {
+        write_file(&directory.join(RULE_QUERY_FILE), query.as_str())?;
+    }
+    if let Some(description) = decode_optional(&rule.description_base64)? {
+        write_file(&directory.join(RULE_DESCRIPTION_FILE), description.as_str())?;
+    }
+
+    let tests_directory = directory.join(RULE_TESTS_DIRECTORY);
+    if !rule.tests.is_empty() {
+        fs::create_dir_all(&tests_directory)
+            .with_context(|| format!("cannot create directory {}", tests_directory.display()))?;
+    }
+    for test in &rule.tests {
+        write_file(
+            &tests_directory.join(&test.filename),
+            decode_base64_string(test.code_base64.clone())?.as_str(),
+        )?;
+    }
+    write_snapshots_to_directory(rule, &tests_directory)
+}
+
+// write the snapshots of the fixed code of the tests of a rule
+fn write_snapshots_to_directory(rule: &Rule, tests_directory: &Path) -> Result<()> {
+    for test in &rule.tests {
+        if let Some(fixed_code) = decode_optional(&test.fixed_code_base64)? {
+            write_file(
+                &get_fixed_code_path(tests_directory, test.filename.as_str()),
+                fixed_code.as_str(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// check the names of a rule and its test files that are used as paths
+fn check_rule_path_names(rule: &Rule) -> Result<()> {
+    check_path_name("rule", get_rule_short_name(rule.name.as_str()))?;
+    for test in &rule.tests {
+        check_path_name("test file", test.filename.as_str())?;
+    }
+    Ok(())
+}
+
+/// Write a ruleset in a ruleset directory (see `get_ruleset_from_directory`). The names
+/// of the ruleset, its rules and their test files are checked with `check_path_name`
+/// before anything is written.
+pub fn write_ruleset_to_directory(ruleset: &RuleSet, directory: &Path) -> Result<()> {
+    check_path_name("ruleset", ruleset.name.as_str())?;
+    for rule in &ruleset.rules {
+        check_rule_path_names(rule)?;
+    }
+    fs::create_dir_all(directory)
+        .with_context(|| format!("cannot create directory {}", directory.display()))?;
+    let metadata = RulesetMetadata {
+        name: ruleset.name.clone(),
+        description: decode_optional(&ruleset.description)?,
+    };
+    write_file(
+        &directory.join(RULESET_METADATA_FILE),
+        serde_yaml::to_string(&metadata)?.as_str(),
+    )?;
+    for rule in &ruleset.rules {
+        write_rule_to_directory(
+            rule,
+            &directory.join(get_rule_short_name(rule.name.as_str())),
+        )?;
+    }
+    Ok(())
+}
+
+/// Write the snapshots of the fixed code of the tests of a ruleset read from a ruleset
+/// directory. Other files of the directory are left untouched and nothing is written if
+/// the name of a rule or a test file is invalid.
+pub fn write_ruleset_snapshots_to_directory(ruleset: &RuleSet, directory: &Path) -> Result<()> {
+    for rule in &ruleset.rules {
+        check_rule_path_names(rule)?;
+    }
+    for rule in &ruleset.rules {
+        let tests_directory = directory
+            .join(get_rule_short_name(rule.name.as_str()))
+            .join(RULE_TESTS_DIRECTORY);
+        write_snapshots_to_directory(rule, &tests_directory)?;
+    }
+    Ok(())
+}
+
 pub fn get_languages_for_rules(rules: &[Rule]) -> Vec<Language> {
     let mut all_languages: Vec<Language> = rules.iter().map(|r| r.language).collect();
     all_languages.dedup();
@@ -28,6 +374,7 @@ mod tests {
     };
 
     use super::*;
+    use crate::test_utils::{create_temp_dir, write_files};
 
     // make sure we correctly get rulesets from a string
     #[test]
@@ -81,4 +428,194 @@ mod tests {
         assert_eq!(rule.description_base64, Some("RW5zdXJlIHRoYXQgc29tZSB3b3JkcyBhcmUgbm90IHVzZWQgaW4gdGhlIGNvZGViYXNlIGFuZCBzdWdnZXN0IHJlcGxhY2VtZW50IHdoZW4gYXBwcm9wcmlhdGUuCgpFeGFtcGxlcyBvZiByZXBsYWNlbWVudCBzdWdnZXN0aW9uczoKIC0gYGJsYWNrbGlzdGAgd2l0aCBgZGVueWxpc3RgCiAtIGB3aGl0ZWxpc3RgIHdpdGggYGFsbG93bGlzdGAKIC0gYG1hc3RlcmAgd2l0aCBgcHJpbWFyeWAKIC0gYHNsYXZlYCB3aXRoIGBzZWNvbmRhcnlg".to_string()));
         assert_eq!(rule.code_base64, "LyoqCiAqIEEgdmlzaXQgZnVuY3Rpb24KICogQHBhcmFtIHthbnl9IG5vZGUgQW4gQVNUIGFueSBub2RlLgogKiBAcGFyYW0ge3N0cmluZ30gZmlsZW5hbWUgQSBmaWxlbmFtZSBwYXJhbS4KICogQHBhcmFtIHtzdHJpbmd9IGNvZGUgQSBjb2RlIHBhcmFtLgogKiBAcmV0dXJucwogKi8KZnVuY3Rpb24gdmlzaXQobm9kZSwgZmlsZW5hbWUsIGNvZGUpIHsKICBjb25zdCBGT1JCSURERU5fTkFNRVMgPSBuZXcgTWFwKCk7CgogIEZPUkJJRERFTl9OQU1FUy5zZXQoImJsYWNrbGlzdCIsICJkZW55bGlzdCIpOwogIEZPUkJJRERFTl9OQU1FUy5zZXQoIndoaXRlbGlzdCIsICJhbGxvd2xpc3QiKTsKICBGT1JCSURERU5fTkFNRVMuc2V0KCJtYXN0ZXIiLCAicHJpbWFyeSIpOwogIEZPUkJJRERFTl9OQU1FUy5zZXQoInNsYXZlIiwgInNlY29uZGFyeSIpOwoKICBmdW5jdGlvbiByZXBsYWNlKHRleHQsIHJlcGxhY2VtZW50LCBwb3NpdGlvbkluVGV4dCkgewogICAgdmFyIHJlc3VsdCA9IHRleHQuc3Vic3RyaW5nKDAsIHBvc2l0aW9uSW5UZXh0KTsKICAgIHZhciBwb3MgPSBwb3NpdGlvbkluVGV4dDsKICAgIGZvcih2YXIgaSA9IDA7IGkgPCByZXBsYWNlbWVudC5sZW5ndGg7IGkrKykgewogICAgICAgIHZhciBjID0gdGV4dC5jaGFyQXQocG9zKTsKICAgICAgICBpZihjID49IDY1ICYmIGMgPCA2NSArIDI2KSB7CiAgICAgICAgICAgIHJlc3VsdCArPSByZXBsYWNlbWVudC5jaGFyQXQoaSkudG9VcHBlckNhc2UoKTsKICAgICAgICB9IGVsc2UgewogICAgICAgICAgICByZXN1bHQgKz0gcmVwbGFjZW1lbnQuY2hhckF0KGkpLnRvTG93ZXJDYXNlKCk7CiAgICAgICAgfQogICAgICAgIHBvcyA9IHBvcyArIDE7CiAgICB9CiAgICByZXN1bHQgPSByZXN1bHQgKyB0ZXh0LnN1YnN0cmluZyhwb3MgKyAxLCB0ZXh0Lmxlbmd0aCk7CiAgICByZXR1cm4gcmVzdWx0OwogIH0KCiAgY29uc3QgaGFuZGxlcklkZW50aWZpZXIgPSAoaWRlbnRpZmllcikgPT4gewogICAgY29uc3QgYyA9IGdldENvZGUoaWRlbnRpZmllci5zdGFydCwgaWRlbnRpZmllci5lbmQsIGNvZGUpOwogICAgZm9yIChsZXQgW2tleSwgdmFsdWVdIG9mIEZPUkJJRERFTl9OQU1FUykgewogICAgICBjb25zdCBwb3MgPSBjLnRvTG93ZXJDYXNlKCkuaW5kZXhPZihrZXkpOwogICAgICBpZiAocG9zICE9PSAtMSkgewogICAgICAgIGNvbnN0IG5ld0NvZGUgPSByZXBsYWNlKGMsIHZhbHVlLCBwb3MpOwogICAgICAgIGNvbnN0IGVyciA9IGJ1aWxkRXJyb3IoCiAgICAgICAgICBpZGVudGlmaWVyLnN0YXJ0LmxpbmUsIGlkZW50aWZpZXIuc3RhcnQuY29sLAogICAgICAgICAgaWRlbnRpZmllci5lbmQubGluZSwgaWRlbnRpZmllci5lbmQuY29sLAogICAgICAgICAgYHN0cmluZyAke2tleX0gZGlzY291cmFnZWRgLAogICAgICAgICAgIldBUk5JTkciLAogICAgICAgICAgIkNPREVfU1RZTEUiCiAgICAgICAgKTsKICAgICAgICBjb25zdCBlID0gYnVpbGRFZGl0VXBkYXRlKAogICAgICAgICAgaWRlbnRpZmllci5zdGFydC5saW5lLCBpZGVudGlmaWVyLnN0YXJ0LmNvbCwKICAgICAgICAgIGlkZW50aWZpZXIuZW5kLmxpbmUsIGlkZW50aWZpZXIuZW5kLmNvbCwKICAgICAgICAgIG5ld0NvZGUKICAgICAgICApOwogICAgICAgIGNvbnN0IGYgPSBidWlsZEZpeChgdXNlICR7dmFsdWV9IGluc3RlYWRgLCBbZV0pOwogICAgICAgIGFkZEVycm9yKGVyci5hZGRGaXgoZikpOwogICAgICB9CiAgICB9CiAgfTsKCiAgaGFuZGxlcklkZW50aWZpZXIobm9kZS5jYXB0dXJlc1siZnVuY3Rpb25uYW1lIl0pOwogIGNvbnN0IHBhcmFtZXRlcnMgPSBub2RlLmNhcHR1cmVzWyJwYXJhbWV0ZXJzIl0uY2hpbGRyZW4uZmlsdGVyKGUgPT4gZS5hc3RUeXBlID09PSAiaWRlbnRpZmllciIpOwogIHBhcmFtZXRlcnMuZm9yRWFjaCgoZSkgPT4gewogICAgaGFuZGxlcklkZW50aWZpZXIoZSk7CiAgfSk7Cn0K".to_string());
     }
+
+    // rulesets written in a file are read back and files of a directory are sorted
+    #[test]
+    fn test_rulesets_files_from_directory() {
+        let temp_dir = create_temp_dir(&[("README.md", "not a ruleset")]);
+        let directory = temp_dir.path();
+        let ruleset = RuleSet {
+            name: "myruleset".to_string(),
+            description: None,
+            rules: vec![],
+            signature: None,
+        };
+        write_rulesets_to_file(&directory.join("b.json"), std::slice::from_ref(&ruleset)).unwrap();
+        write_rulesets_to_file(&directory.join("a.json"), &[ruleset]).unwrap();
+
+        let files = get_rulesets_files_from_directory(directory.to_str().unwrap()).unwrap();
+        assert_eq!(2, files.len());
+        assert!(files[0].ends_with("a.json"));
+        assert!(files[1].ends_with("b.json"));
+        let rulesets = get_rulesets_from_file(files[0].as_str()).unwrap();
+        assert_eq!("myruleset", rulesets[0].name);
+    }
+
+    // read a ruleset directory, the checksums are computed
+    #[test]
+    fn test_get_ruleset_from_directory() {
+        let temp_dir = create_temp_dir(&[
+            ("ruleset.yaml", "name: python-test\ndescription: My rules\n"),
+            (
+                "no-foo/rule.yaml",
+                r#"
+short_description: Do not use foo
+category: BEST_PRACTICES
+severity: WARNING
+language: PYTHON
+tests:
+  foo.py:
+    annotation_count: 1
+"#,
+            ),
+            ("no-foo/query.scm", "(function_definition) @f"),
+            ("no-foo/visit.js", "function visit(node) {}"),
+            ("no-foo/description.md", "# No foo"),
+            ("no-foo/tests/foo.py", "def foo():\n  pass\n"),
+            ("no-foo/tests/foo.py.fixed", "def bar():\n  pass\n"),
+            ("no-foo/tests/bar.py", "def bar():\n  pass\n"),
+            ("README.md", "not a rule"),
+        ]);
+        let directory = temp_dir.path();
+
+        let ruleset = get_ruleset_from_directory(directory.to_str().unwrap()).unwrap();
+        assert_eq!("python-test", ruleset.name);
+        assert_eq!(
+            Some(encode_base64_string("My rules".to_string())),
+            ruleset.description
+        );
+        assert_eq!(1, ruleset.rules.len());
+        let rule = &ruleset.rules[0];
+        assert_eq!("python-test/no-foo", rule.name);
+        assert_eq!(RuleType::TreeSitterQuery, rule.rule_type);
+        assert_eq!(Language::Python, rule.language);
+        assert!(rule.verify_checksum());
+        assert_eq!(
+            "(function_definition) @f",
+            decode_base64_string(rule.tree_sitter_query_base64.clone().unwrap()).unwrap()
+        );
+        assert_eq!(2, rule.tests.len());
+        assert_eq!("bar.py", rule.tests[0].filename);
+        assert_eq!(0, rule.tests[0].annotation_count);
+        assert!(rule.tests[0].fixed_code_base64.is_none());
+        assert_eq!("foo.py", rule.tests[1].filename);
+        assert_eq!(1, rule.tests[1].annotation_count);
+        assert_eq!(
+            Some(encode_base64_string("def bar():\n  pass\n".to_string())),
+            rule.tests[1].fixed_code_base64
+        );
+
+        // metadata of a test without code
+        write_files(
+            directory,
+            &[(
+                "no-foo/rule.yaml",
+                "category: BEST_PRACTICES\nseverity: WARNING\nlanguage: PYTHON\ntests:\n  baz.py: {}\n",
+            )],
+        );
+        assert!(get_ruleset_from_directory(directory.to_str().unwrap()).is_err());
+    }
+
+    // a ruleset written in a directory is read back
+    #[test]
+    fn test_write_ruleset_to_directory() {
+        let temp_dir = create_temp_dir(&[]);
+        let parent = temp_dir.path();
+        let directory = parent.join("python-export");
+        let mut rulesets = get_rulesets_from_reader(
+            r#"[{"name": "python-export", "description": null, "rules": [{
+                "name": "python-export/my-rule",
+                "short_description": "c2hvcnQ=",
+                "description": null,
+                "category": "SECURITY",
+                "severity": "ERROR",
+                "language": "PYTHON",
+                "rule_type": "TREE_SITTER_QUERY",
+                "entity_checked": null,
+                "code": "ZnVuY3Rpb24gdmlzaXQoKSB7fQ==",
+                "checksum": "",
+                "pattern": null,
+                "tree_sitter_query": "KGlkZW50aWZpZXIpIEBpZA==",
+                "variables": {"foo": "bar"},
+                "tests": [{"annotation_count": 2, "filename": "test.py", "code": "Zm9vKCk=", "fixed_code": "YmFyKCk="}]
+            }]}]"#
+                .as_bytes(),
+        )
+        .unwrap();
+        let ruleset = rulesets.remove(0);
+        write_ruleset_to_directory(&ruleset, &directory).unwrap();
+        assert!(is_ruleset_directory(&directory));
+        assert_eq!(
+            "bar()",
+            fs::read_to_string(directory.join("my-rule/tests/test.py.fixed")).unwrap()
+        );
+
+        let read = get_ruleset_from_directory(directory.to_str().unwrap()).unwrap();
+        assert_eq!(ruleset.name, read.name);
+        let (expected, rule) = (&ruleset.rules[0], &read.rules[0]);
+        assert_eq!(expected.name, rule.name);
+        assert_eq!(
+            expected.short_description_base64,
+            rule.short_description_base64
+        );
+        assert_eq!(expected.code_base64, rule.code_base64);
+        assert_eq!(
+            expected.tree_sitter_query_base64,
+            rule.tree_sitter_query_base64
+        );
+        assert_eq!(expected.variables, rule.variables);
+        assert_eq!(expected.category, rule.category);
+        assert!(rule.verify_checksum());
+        assert_eq!(2, rule.tests[0].annotation_count);
+        assert_eq!(expected.tests[0].code_base64, rule.tests[0].code_base64);
+        assert_eq!(
+            expected.tests[0].fixed_code_base64,
+            rule.tests[0].fixed_code_base64
+        );
+
+        assert_eq!(
+            vec![directory.to_str().unwrap().to_string()],
+            get_rulesets_directories_from_directory(parent.to_str().unwrap()).unwrap()
+        );
+
+        // names that would write outside of the directory are rejected before writing
+        let mut invalid = ruleset.clone();
+        invalid.rules[0].name = "python-export/..".to_string();
+        let invalid_directory = parent.join("invalid");
+        assert_eq!(
+            "invalid rule name ..",
+            write_ruleset_to_directory(&invalid, &invalid_directory)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(!invalid_directory.exists());
+
+        // test files are checked before the ruleset or any rule is written
+        let mut invalid = ruleset.clone();
+        invalid.rules.push(ruleset.rules[0].clone());
+        invalid.rules[1].name = "python-export/other-rule".to_string();
+        invalid.rules[1].tests[0].filename = "../test.py".to_string();
+        assert_eq!(
+            "invalid test file name ../test.py",
+            write_ruleset_to_directory(&invalid, &invalid_directory)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(!invalid_directory.exists());
+        invalid.rules[1].name = "python-export/my-rule".to_string();
+        fs::remove_file(directory.join("my-rule/tests/test.py.fixed")).unwrap();
+        assert!(write_ruleset_snapshots_to_directory(&invalid, &directory).is_err());
+        assert!(!directory.join("my-rule/tests/test.py.fixed").exists());
+    }
+
+    #[test]
+    fn test_check_path_name() {
+        assert!(check_path_name("ruleset", "python-security").is_ok());
+        assert!(check_path_name("test file", "test.py").is_ok());
+        for name in [
+            "", ".", "..", "../foo", "foo/bar", "foo\\bar", "/etc", "a..b",
+        ] {
+            assert!(check_path_name("ruleset", name).is_err(), "{}", name);
+        }
+    }
 }
@@ -0,0 +1,440 @@
+use anyhow::{anyhow, Result};
+use kernel::model::ruleset::RuleSet;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::datadog_utils::{get_ruleset_url, request_ruleset, RulesetResponse, RulesetValidators};
+use crate::rule_utils::check_path_name;
+
+// a ruleset saved in the cache, with what is needed to revalidate it
+#[derive(Clone, Deserialize, Debug, Serialize)]
+struct CachedRuleset {
+    // when the ruleset was fetched or last revalidated, in seconds since epoch
+    fetched_at: u64,
+    validators: RulesetValidators,
+    ruleset: RuleSet,
+}
+
+// where a ruleset comes from
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RulesetOrigin {
+    // downloaded from the API
+    Api,
+    // read from the cache, without querying the API since it is not expired
+    Cache,
+    // read from the cache after the API confirmed it did not change
+    Revalidated,
+    // read from the cache because the API could not be queried (or with --offline)
+    Offline,
+}
+
+// a ruleset and where it comes from. The age is the time since the ruleset was fetched
+// and the error is the reason why the API could not be queried, if any.
+pub struct CacheResult {
+    pub ruleset: RuleSet,
+    pub origin: RulesetOrigin,
+    pub age: Duration,
+    pub error: Option<String>,
+}
+
+// Cache of the rulesets from the API, one file per site and ruleset
+pub struct RulesetCache {
+    pub directory: PathBuf,
+    // how long a ruleset is used without querying the API
+    pub ttl: Duration,
+    // only use the rulesets from the cache and never query the API
+    pub offline: bool,
+}
+
+// Whether an error of the API is worth falling back to the cache: the API could not be
+// reached or failed (5xx). Other errors (e.g. 401, 403, 404 or an invalid response) are
+// reported to the user, the cached ruleset may be as wrong as the request.
+fn is_transient_error(error: &anyhow::Error) -> bool {
+    match error
+        .chain()
+        .find_map(|e| e.downcast_ref::<reqwest::Error>())
+    {
+        Some(e) => match e.status() {
+            Some(status) => status.is_server_error(),
+            None => !e.is_decode(),
+        },
+        None => false,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Human readable age of a ruleset (e.g. 5 min)
+pub fn format_age(age: &Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        s if s < 60 => format!("{} s", s),
+        s if s < 3600 => format!("{} min", s / 60),
+        s if s < 86400 => format!("{} h", s / 3600),
+        s => format!("{} days", s / 86400),
+    }
+}
+
+// Get the default directory of the cache: $XDG_CACHE_HOME, ~/.cache or the temporary
+// directory if none is defined.
+pub fn get_default_cache_directory() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".cache")))
+        .unwrap_or(std::env::temp_dir())
+        .join("datadog-static-analyzer")
+}
+
+impl RulesetCache {
+    // the site, name and version come from the environment and the configuration file,
+    // they are checked so that the cache is never written outside of its directory
+    fn get_path(&self, site: &str, ruleset_name: &str, version: Option<&str>) -> Result<PathBuf> {
+        check_path_name("site", site)?;
+        check_path_name("ruleset", ruleset_name)?;
+        let filename = match version {
+            Some(v) => {
+                check_path_name("ruleset version", v)?;
+                format!("{}@{}.json", ruleset_name, v)
+            }
+            None => format!("{}.json", ruleset_name),
+        };
+        Ok(self.directory.join(site).join(filename))
+    }
+
+    fn read(&self, path: &Path) -> Option<CachedRuleset> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn write(&self, path: &Path, cached: &CachedRuleset) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // write a temporary file first so that a ruleset is never partially written
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string(cached)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    // a ruleset that cannot be cached is still used, the cache is only an optimization
+    fn write_or_warn(&self, path: &Path, cached: &CachedRuleset) {
+        if let Err(e) = self.write(path, cached) {
+            eprintln!(
+                "warning: cannot cache ruleset {} in {}: {}",
+                cached.ruleset.name,
+                path.display(),
+                e
+            );
+        }
+    }
+
+    // Get a ruleset from the API of the Datadog site, using the cache if possible.
+    pub fn get_ruleset(
+        &self,
+        site: &str,
+        ruleset_name: &str,
+        version: Option<&str>,
+    ) -> Result<CacheResult> {
+        let url = get_ruleset_url(site, ruleset_name, version)?;
+        self.get_ruleset_from_url(url.as_str(), site, ruleset_name, version)
+    }
+
+    fn get_ruleset_from_url(
+        &self,
+        url: &str,
+        site: &str,
+        ruleset_name: &str,
+        version: Option<&str>,
+    ) -> Result<CacheResult> {
+        let path = self.get_path(site, ruleset_name, version)?;
+        let cached = self.read(&path);
+        let age = |c: &CachedRuleset| Duration::from_secs(now().saturating_sub(c.fetched_at));
+
+        if self.offline {
+            return match cached {
+                Some(c) => Ok(CacheResult {
+                    age: age(&c),
+                    ruleset: c.ruleset,
+                    origin: RulesetOrigin::Offline,
+                    error: None,
+                }),
+                None => Err(anyhow!("ruleset {} is not in the cache", ruleset_name)),
+            };
+        }
+
+        if let Some(c) = &cached {
+            if age(c) < self.ttl {
+                return Ok(CacheResult {
+                    age: age(c),
+                    ruleset: c.ruleset.clone(),
+                    origin: RulesetOrigin::Cache,
+                    error: None,
+                });
+            }
+        }
+
+        let validators = cached
+            .as_ref()
+            .map(|c| c.validators.clone())
+            .unwrap_or_default();
+        match (request_ruleset(url, &validators), cached) {
+            (
+                Ok(RulesetResponse::Modified {
+                    ruleset,
+                    validators,
+                }),
+                _,
+            ) => {
+                let cached = CachedRuleset {
+                    fetched_at: now(),
+                    validators,
+                    ruleset,
+                };
+                self.write_or_warn(&path, &cached);
+                Ok(CacheResult {
+                    ruleset: cached.ruleset,
+                    origin: RulesetOrigin::Api,
+                    age: Duration::ZERO,
+                    error: None,
+                })
+            }
+            (Ok(RulesetResponse::NotModified), Some(mut c)) => {
+                c.fetched_at = now();
+                self.write_or_warn(&path, &c);
+                Ok(CacheResult {
+                    ruleset: c.ruleset,
+                    origin: RulesetOrigin::Revalidated,
+                    age: Duration::ZERO,
+                    error: None,
+                })
+            }
+            (Ok(RulesetResponse::NotModified), None) => Err(anyhow!(
+                "ruleset {} not modified but not in the cache",
+                ruleset_name
+            )),
+            // the API cannot be queried, use the cached ruleset even if it is expired
+            (Err(e), Some(c)) if is_transient_error(&e) => Ok(CacheResult {
+                age: age(&c),
+                ruleset: c.ruleset,
+                origin: RulesetOrigin::Offline,
+                error: Some(format!("{:#}", e)),
+            }),
+            (Err(e), _) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_temp_dir;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use tempfile::TempDir;
+
+    const RULESET_RESPONSE: &str =
+        r#"{"data": {"attributes": {"name": "python-security", "description": "", "rules": []}}}"#;
+
+    // status, headers and body of a response of the test server
+    type Response = (u16, Vec<(&'static str, &'static str)>, &'static str);
+
+    // Start a local server that answers the requests with the given responses, one
+    // response per request. Returns the URL of the server and the requests it received.
+    fn start_server(responses: Vec<Response>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ruleset", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+        let requests_server = requests.clone();
+        thread::spawn(move || {
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![];
+                let mut buffer = [0; 1024];
+                while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                requests_server
+                    .lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&request).to_lowercase());
+                let mut response = format!(
+                    "HTTP/1.1 {} STATUS\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    status,
+                    body.len()
+                );
+                for (name, value) in headers {
+                    response.push_str(format!("{}: {}\r\n", name, value).as_str());
+                }
+                response.push_str("\r\n");
+                response.push_str(body);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    // a cache in a temporary directory, removed when the directory is dropped
+    fn cache(ttl: Duration, offline: bool) -> (TempDir, RulesetCache) {
+        let temp_dir = create_temp_dir(&[]);
+        let cache = RulesetCache {
+            directory: temp_dir.path().to_path_buf(),
+            ttl,
+            offline,
+        };
+        (temp_dir, cache)
+    }
+
+    #[test]
+    fn test_ruleset_cache_revalidation() {
+        let (url, requests) = start_server(vec![
+            (200, vec![("ETag", "\"v1\"")], RULESET_RESPONSE),
+            (304, vec![], ""),
+        ]);
+        let (_temp_dir, cache) = cache(Duration::ZERO, false);
+
+        let result = cache
+            .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+            .unwrap();
+        assert_eq!(RulesetOrigin::Api, result.origin);
+        assert_eq!("python-security", result.ruleset.name);
+
+        // the cached ruleset is expired, it is revalidated with its ETag
+        let result = cache
+            .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+            .unwrap();
+        assert_eq!(RulesetOrigin::Revalidated, result.origin);
+        assert_eq!("python-security", result.ruleset.name);
+        let requests = requests.lock().unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+
+        // the API is not reachable anymore, the cached ruleset is used
+        let result = cache
+            .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+            .unwrap();
+        assert_eq!(RulesetOrigin::Offline, result.origin);
+        assert!(result
+            .error
+            .unwrap()
+            .starts_with("error querying rulesets: "));
+    }
+
+    #[test]
+    fn test_ruleset_cache_api_errors() {
+        let (url, _) = start_server(vec![
+            (200, vec![], RULESET_RESPONSE),
+            (503, vec![], ""),
+            (403, vec![], ""),
+            (404, vec![], ""),
+            (200, vec![], "{"),
+        ]);
+        let (_temp_dir, cache) = cache(Duration::ZERO, false);
+        let get = || cache.get_ruleset_from_url(url.as_str(), "site", "python-security", None);
+
+        assert_eq!(RulesetOrigin::Api, get().unwrap().origin);
+        // the API fails, the cached ruleset is used
+        let result = get().unwrap();
+        assert_eq!(RulesetOrigin::Offline, result.origin);
+        assert!(result.error.unwrap().contains("503"));
+        // the request is invalid, it is reported even if the ruleset is cached
+        assert!(get()
+            .err()
+            .unwrap()
+            .root_cause()
+            .to_string()
+            .contains("403"));
+        assert!(get()
+            .err()
+            .unwrap()
+            .root_cause()
+            .to_string()
+            .contains("404"));
+        assert_eq!(
+            "error when parsing the server response",
+            get().err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_ruleset_cache_ttl_and_offline() {
+        let (url, requests) = start_server(vec![(200, vec![], RULESET_RESPONSE)]);
+
+        // nothing in the cache
+        let (_temp_dir, offline_cache) = cache(Duration::ZERO, true);
+        assert_eq!(
+            "ruleset python-security is not in the cache",
+            offline_cache
+                .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+                .err()
+                .unwrap()
+                .to_string()
+        );
+
+        let cache = RulesetCache {
+            ttl: Duration::from_secs(3600),
+            offline: false,
+            ..offline_cache
+        };
+        for origin in [RulesetOrigin::Api, RulesetOrigin::Cache] {
+            let result = cache
+                .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+                .unwrap();
+            assert_eq!(origin, result.origin);
+        }
+        assert_eq!(1, requests.lock().unwrap().len());
+
+        let offline_cache = RulesetCache {
+            offline: true,
+            ..cache
+        };
+        let result = offline_cache
+            .get_ruleset_from_url(url.as_str(), "site", "python-security", None)
+            .unwrap();
+        assert_eq!(RulesetOrigin::Offline, result.origin);
+        // pinned versions are cached separately
+        assert!(offline_cache
+            .get_ruleset_from_url(url.as_str(), "site", "python-security", Some("2"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_ruleset_cache_invalid_path() {
+        let (_temp_dir, cache) = cache(Duration::ZERO, true);
+        for (site, name, version) in [
+            ("../site", "python-security", None),
+            ("site", "../python-security", None),
+            ("site", "python-security", Some("../../../x")),
+            ("site", "python-security", Some("1/2")),
+        ] {
+            assert!(cache
+                .get_ruleset_from_url("http://127.0.0.1:1/", site, name, version)
+                .err()
+                .unwrap()
+                .to_string()
+                .starts_with("invalid "));
+        }
+        assert!(cache
+            .get_path("datadoghq.com", "python-security", Some("1.0.2"))
+            .unwrap()
+            .starts_with(&cache.directory));
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!("12 s", format_age(&Duration::from_secs(12)));
+        assert_eq!("2 min", format_age(&Duration::from_secs(150)));
+        assert_eq!("3 h", format_age(&Duration::from_secs(3 * 3600 + 10)));
+        assert_eq!("2 days", format_age(&Duration::from_secs(2 * 86400)));
+    }
+}
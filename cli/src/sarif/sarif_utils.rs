@@ -7,10 +7,12 @@ use serde_sarif::sarif::{
     ReportingDescriptor, Result as SarifResult, ResultBuilder, RunBuilder, Sarif, SarifBuilder,
     Tool, ToolBuilder, ToolComponent, ToolComponentBuilder,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use std::rc::Rc;
 
+use crate::model::git_diff::GitDiff;
+use kernel::constants::{CARGO_VERSION, TREE_SITTER_GRAMMARS};
 use kernel::model::rule::RuleSeverity;
 use kernel::model::{
     common::PositionBuilder,
@@ -143,17 +145,34 @@ impl IntoSarif for &Edit {
     }
 }
 
-// Generate the tool section that reports all the rules being run
+// Generate the tool section that reports all the rules being run. The tree-sitter
+// grammars used to parse the code are reported in the properties of the driver
+// (treeSitterGrammars) with their revision, so that we know exactly which parsers
+// produced the results.
 fn generate_tool_section(rules: &[Rule]) -> Result<Tool> {
+    let grammars = TREE_SITTER_GRAMMARS
+        .iter()
+        .map(|(name, revision)| serde_json::json!({"name": name, "revision": revision}))
+        .collect::<Vec<serde_json::Value>>();
+
     let driver: ToolComponent = ToolComponentBuilder::default()
         .name("datadog-static-analyzer")
         .information_uri("https://www.datadoghq.com")
+        .version(CARGO_VERSION)
         .rules(
             rules
                 .iter()
                 .map(|e| e.into_sarif())
                 .collect::<Vec<ReportingDescriptor>>(),
         )
+        .properties(
+            PropertyBagBuilder::default()
+                .additional_properties(BTreeMap::from([(
+                    "treeSitterGrammars".to_string(),
+                    serde_json::Value::from(grammars),
+                )]))
+                .build()?,
+        )
         .build()?;
 
     Ok(ToolBuilder::default().driver(driver).build()?)
@@ -201,6 +220,7 @@ fn generate_results(
     rules: &[Rule],
     rules_results: &[RuleResult],
     options_orig: SarifGenerationOptions,
+    config_files: &HashMap<String, String>,
 ) -> Result<Vec<SarifResult>> {
     rules_results
         .iter()
@@ -219,6 +239,11 @@ fn generate_results(
                 // Why not json_serde::to_value?
             }
 
+            // the configuration file that applies to the file of the violation
+            if let Some(config_file) = config_files.get(&rule_result.filename) {
+                category_tags.push(format!("DATADOG_CONFIG_FILE:{}", config_file));
+            }
+
             let options = options_orig.clone();
             rule_result.violations.iter().map(move |violation| {
                 // if we find the rule for this violation, get the id, level and category
@@ -275,10 +300,15 @@ fn generate_results(
                     violation.start.line as usize,
                     &options,
                 );
-                let partial_fingerprints: BTreeMap<String, String> = match sha_option {
+                let mut partial_fingerprints: BTreeMap<String, String> = match sha_option {
                     Some(s) => BTreeMap::from([("SHA".to_string(), s)]),
                     None => BTreeMap::new(),
                 };
+                // fingerprint of the content of the violation (see `Violation::compute_fingerprint`)
+                if let Some(fingerprint) = &violation.fingerprint {
+                    partial_fingerprints
+                        .insert("DATADOG_FINGERPRINT".to_string(), fingerprint.clone());
+                }
 
                 Ok(result_builder
                     .clone()
@@ -307,12 +337,16 @@ fn generate_results(
 // generate a SARIF report for a run.
 // the rules parameter is the list of rules used for this run
 // the violations parameter is the list of violations for this run.
+// the config_files parameter is the configuration file used for each file, if any.
+// the git_diff parameter is the diff analyzed, if only the changed files are analyzed.
 pub fn generate_sarif_report(
     rules: &[Rule],
     rules_results: &[RuleResult],
     directory: &String,
     add_git_info: bool,
     debug: bool,
+    config_files: &HashMap<String, String>,
+    git_diff: Option<&GitDiff>,
 ) -> Result<Sarif> {
     // if we enable git info, we are then getting the repository object. We put that
     // into an `Arc` object to be able to clone the object.
@@ -333,10 +367,33 @@ pub fn generate_sarif_report(
         debug,
     };
 
-    let run = RunBuilder::default()
+    let mut run_builder = RunBuilder::default();
+    run_builder
         .tool(generate_tool_section(rules)?)
-        .results(generate_results(rules, rules_results, options)?)
-        .build()?;
+        .results(generate_results(
+            rules,
+            rules_results,
+            options,
+            config_files,
+        )?);
+    // record the commits of the diff when only the changed files are analyzed
+    if let Some(diff) = git_diff {
+        run_builder.properties(
+            PropertyBagBuilder::default()
+                .additional_properties(BTreeMap::from([
+                    (
+                        "baseCommit".to_string(),
+                        serde_json::Value::from(diff.base_commit.clone()),
+                    ),
+                    (
+                        "headCommit".to_string(),
+                        serde_json::Value::from(diff.head_commit.clone()),
+                    ),
+                ]))
+                .build()?,
+        );
+    }
+    let run = run_builder.build()?;
 
     Ok(SarifBuilder::default()
         .version("2.1.0")
@@ -425,14 +482,20 @@ mod tests {
             &"mydir".to_string(),
             false,
             false,
+            &HashMap::new(),
+            None,
         )
         .expect("generate sarif report");
 
         let sarif_report_to_string = serde_json::to_value(sarif_report).unwrap();
         println!("{}", sarif_report_to_string);
+        let grammars: Vec<serde_json::Value> = TREE_SITTER_GRAMMARS
+            .iter()
+            .map(|(name, revision)| serde_json::json!({"name": name, "revision": revision}))
+            .collect();
         assert_json_eq!(
             sarif_report_to_string,
-            serde_json::json!({"runs":[{"results":[{"fixes":[{"artifactChanges":[{"artifactLocation":{"uri":"myfile"},"replacements":[{"deletedRegion":{"endColumn":6,"endLine":6,"startColumn":6,"startLine":6},"insertedContent":{"text":"newcontent"}}]}],"description":{"text":"myfix"}}],"level":"error","locations":[{"physicalLocation":{"artifactLocation":{"uri":"myfile"},"region":{"endColumn":4,"endLine":3,"startColumn":2,"startLine":1}}}],"message":{"text":"violation message"},"partialFingerprints":{},"properties":{"tags":["DATADOG_CATEGORY:BEST_PRACTICES"]},"ruleId":"my-rule","ruleIndex":0}],"tool":{"driver":{"informationUri":"https://www.datadoghq.com","name":"datadog-static-analyzer","rules":[{"fullDescription":{"text":"awesome rule"},"helpUri":"https://docs.datadoghq.com/continuous_integration/static_analysis/rules/my-rule","id":"my-rule","shortDescription":{"text":"short description"}}]}}}],"version":"2.1.0"})
+            serde_json::json!({"runs":[{"results":[{"fixes":[{"artifactChanges":[{"artifactLocation":{"uri":"myfile"},"replacements":[{"deletedRegion":{"endColumn":6,"endLine":6,"startColumn":6,"startLine":6},"insertedContent":{"text":"newcontent"}}]}],"description":{"text":"myfix"}}],"level":"error","locations":[{"physicalLocation":{"artifactLocation":{"uri":"myfile"},"region":{"endColumn":4,"endLine":3,"startColumn":2,"startLine":1}}}],"message":{"text":"violation message"},"partialFingerprints":{},"properties":{"tags":["DATADOG_CATEGORY:BEST_PRACTICES"]},"ruleId":"my-rule","ruleIndex":0}],"tool":{"driver":{"informationUri":"https://www.datadoghq.com","name":"datadog-static-analyzer","version":CARGO_VERSION,"rules":[{"fullDescription":{"text":"awesome rule"},"helpUri":"https://docs.datadoghq.com/continuous_integration/static_analysis/rules/my-rule","id":"my-rule","shortDescription":{"text":"short description"}}],"properties":{"treeSitterGrammars":grammars}}}}],"version":"2.1.0"})
         );
 
         // validate the schema
@@ -468,6 +531,7 @@ mod tests {
                 .start(PositionBuilder::default().line(1).col(2).build().unwrap())
                 .end(PositionBuilder::default().line(3).col(4).build().unwrap())
                 .message("violation message".to_string())
+                .fingerprint(Some("fingerprint".to_string()))
                 .severity(RuleSeverity::Error)
                 .category(RuleCategory::BestPractices)
                 .fixes(vec![RosieFixBuilder::default()
@@ -498,9 +562,18 @@ mod tests {
             &"mydir".to_string(),
             false,
             false,
+            &HashMap::from([(
+                "myfile".to_string(),
+                "static-analysis.datadog.yml".to_string(),
+            )]),
+            Some(&GitDiff {
+                base_commit: "basecommit".to_string(),
+                head_commit: "headcommit".to_string(),
+                ..GitDiff::default()
+            }),
         )
         .expect("generate sarif report");
-        assert!(sarif_report
+        let result = sarif_report
             .runs
             .get(0)
             .unwrap()
@@ -508,10 +581,27 @@ mod tests {
             .as_ref()
             .unwrap()
             .get(0)
-            .unwrap()
-            .rule_index
-            .is_none());
+            .unwrap();
+        assert!(result.rule_index.is_none());
+        // the configuration file of the file is reported
+        assert_eq!(
+            &Some(vec![
+                "DATADOG_CONFIG_FILE:static-analysis.datadog.yml".to_string()
+            ]),
+            &result.properties.as_ref().unwrap().tags
+        );
+        // the commits of the diff are reported
+        let sarif_report_value = serde_json::to_value(sarif_report).unwrap();
+        assert_eq!(
+            serde_json::json!({"baseCommit": "basecommit", "headCommit": "headcommit"}),
+            sarif_report_value["runs"][0]["properties"]
+        );
+        // the fingerprint of the violation is reported
+        assert_eq!(
+            serde_json::json!({"DATADOG_FINGERPRINT": "fingerprint"}),
+            sarif_report_value["runs"][0]["results"][0]["partialFingerprints"]
+        );
         // validate the schema
-        assert!(validate_data(&serde_json::to_value(sarif_report).unwrap()));
+        assert!(validate_data(&sarif_report_value));
     }
 }
@@ -0,0 +1,21 @@
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+// Write files in a directory, with their path relative to the directory and their
+// content. The directories of the files are created.
+pub fn write_files(directory: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = directory.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+}
+
+// Create a temporary directory with some files (see `write_files`). The directory is
+// unique and removed when the returned value is dropped, even if the test fails.
+pub fn create_temp_dir(files: &[(&str, &str)]) -> TempDir {
+    let directory = tempfile::tempdir().unwrap();
+    write_files(directory.path(), files);
+    directory
+}
@@ -1,6 +1,15 @@
+use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+// When set, the grammars are built from this directory (vendored sources or local
+// checkouts, one directory per grammar) and nothing is fetched from the network.
+const GRAMMARS_DIR_ENV: &str = "DD_TREE_SITTER_GRAMMARS_DIR";
+// File of vendored sources (directories that are not git repositories) with the
+// revision of the grammar they were copied from, as pinned below (commit or tag).
+const VENDORED_REVISION_FILE: &str = "REVISION";
+
 fn run<F>(name: &str, mut configure: F)
 where
     F: FnMut(&mut Command) -> &mut Command,
@@ -8,22 +17,68 @@ where
     let mut command = Command::new(name);
     println!("Running {command:?}");
     let configured = configure(&mut command);
+    let status = configured.status();
     assert!(
-        configured.status().is_ok(),
+        status.map(|s| s.success()).unwrap_or(false),
         "failed to execute {configured:?}"
     );
 }
 
+// resolve a git revision (commit, tag) into a commit hash in a repository.
+// Returns None if the directory is not a git repository or the revision is unknown.
+fn git_rev_parse(dir: &Path, revision: &str) -> Option<String> {
+    // vendored sources may live inside another repository: do not use its revision
+    if !dir.join(".git").exists() {
+        return None;
+    }
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("rev-parse")
+        .arg("--verify")
+        .arg("--quiet")
+        .arg(format!("{revision}^{{commit}}"))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+// Get the revision of the sources of a grammar: the commit checked out for a git
+// repository or the revision recorded in the vendored sources. The build fails if
+// the revision cannot be found, we never assume sources match the pinned revision.
+fn get_project_revision(dir: &Path, name: &str) -> String {
+    if let Some(head) = git_rev_parse(dir, "HEAD") {
+        return head;
+    }
+    let revision_file = dir.join(VENDORED_REVISION_FILE);
+    println!("cargo:rerun-if-changed={}", revision_file.display());
+    match fs::read_to_string(&revision_file) {
+        Ok(revision) if !revision.trim().is_empty() => revision.trim().to_string(),
+        _ => panic!(
+            "cannot check the revision of {}: {} is not a git repository and has no {} file",
+            name,
+            dir.display(),
+            VENDORED_REVISION_FILE
+        ),
+    }
+}
+
 fn main() {
     struct TreeSitterProject {
         name: String,       // the directory where we clone the project
+        library: String,    // the name of the library we build
         repository: String, // the repository to clone
+        revision: String,   // the commit (or tag) we build
         build_dir: PathBuf, // the directory we use to build the tree-sitter project
         files: Vec<String>,
     }
 
-    fn compile_project(tree_sitter_project: &TreeSitterProject) {
-        let dir = &tree_sitter_project.build_dir;
+    fn compile_project(tree_sitter_project: &TreeSitterProject, dir: &Path) {
         let files: Vec<PathBuf> = tree_sitter_project
             .files
             .iter()
@@ -34,78 +89,237 @@ fn main() {
             .include(dir)
             .files(files)
             .warnings(false)
-            .compile(tree_sitter_project.name.as_str());
+            .compile(tree_sitter_project.library.as_str());
+    }
+
+    // clone the project if needed and checkout the pinned revision. The revision is
+    // fetched only if it is not already in the local clone. Directories that are not
+    // git repositories are vendored sources, checked against the pinned revision later.
+    fn checkout_project(tree_sitter_project: &TreeSitterProject, dir: &Path) {
+        if !dir.exists() {
+            run("git", |command| {
+                command
+                    .arg("clone")
+                    .arg("--quiet")
+                    .arg(tree_sitter_project.repository.as_str())
+                    .arg(dir)
+            });
+        } else if !dir.join(".git").exists() {
+            return;
+        }
+
+        let revision = tree_sitter_project.revision.as_str();
+        let wanted = git_rev_parse(dir, revision);
+        if wanted.is_some() && wanted == git_rev_parse(dir, "HEAD") {
+            return;
+        }
+        if wanted.is_none() {
+            run("git", |command| {
+                command
+                    .arg("-C")
+                    .arg(dir)
+                    .arg("fetch")
+                    .arg("--quiet")
+                    .arg("--tags")
+                    .arg("origin")
+                    .arg(revision)
+            });
+        }
+        run("git", |command| {
+            command
+                .arg("-C")
+                .arg(dir)
+                .arg("checkout")
+                .arg("--quiet")
+                .arg(revision)
+        });
     }
 
     let tree_sitter_projects: Vec<TreeSitterProject> = vec![
+        TreeSitterProject {
+            name: "tree-sitter-bash".to_string(),
+            library: "tree-sitter-bash".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-bash.git".to_string(),
+            revision: "487734f87fd87118028a65a4599352fa99c9cde8".to_string(),
+            build_dir: ["tree-sitter-bash", "src"].iter().collect(),
+            files: vec!["parser.c".to_string(), "scanner.c".to_string()],
+        },
+        TreeSitterProject {
+            name: "tree-sitter-c".to_string(),
+            library: "tree-sitter-c".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-c.git".to_string(),
+            revision: "25ca2718aacaf0feda8b0709471332f303a0cef5".to_string(),
+            build_dir: ["tree-sitter-c", "src"].iter().collect(),
+            files: vec!["parser.c".to_string()],
+        },
         TreeSitterProject {
             name: "tree-sitter-c-sharp".to_string(),
+            library: "tree-sitter-c-sharp".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-c-sharp.git".to_string(),
+            revision: "362a8a41b265056592a0c3771664a21d23a71392".to_string(),
             build_dir: ["tree-sitter-c-sharp", "src"].iter().collect(),
             files: vec!["parser.c".to_string(), "scanner.c".to_string()],
         },
+        TreeSitterProject {
+            name: "tree-sitter-cpp".to_string(),
+            library: "tree-sitter-cpp".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-cpp.git".to_string(),
+            revision: "e0c1678a78731e78655b7d953efb4daecf58be46".to_string(),
+            build_dir: ["tree-sitter-cpp", "src"].iter().collect(),
+            files: vec!["parser.c".to_string(), "scanner.c".to_string()],
+        },
         TreeSitterProject {
             name: "tree-sitter-dockerfile".to_string(),
+            library: "tree-sitter-dockerfile".to_string(),
             repository: "https://github.com/camdencheek/tree-sitter-dockerfile.git".to_string(),
+            revision: "v0.1.2".to_string(),
             build_dir: ["tree-sitter-dockerfile", "src"].iter().collect(),
             files: vec!["parser.c".to_string()],
         },
         TreeSitterProject {
             name: "tree-sitter-go".to_string(),
+            library: "tree-sitter-go".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-go.git".to_string(),
+            revision: "bbaa67a180cfe0c943e50c55130918be8efb20bd".to_string(),
             build_dir: ["tree-sitter-go", "src"].iter().collect(),
             files: vec!["parser.c".to_string()],
         },
         TreeSitterProject {
             name: "tree-sitter-java".to_string(),
+            library: "tree-sitter-java".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-java.git".to_string(),
+            revision: "2b57cd9541f9fd3a89207d054ce8fbe72657c444".to_string(),
             build_dir: ["tree-sitter-java", "src"].iter().collect(),
             files: vec!["parser.c".to_string()],
         },
         TreeSitterProject {
             name: "tree-sitter-javascript".to_string(),
+            library: "tree-sitter-javascript".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-javascript.git".to_string(),
+            revision: "de1e682289a417354df5b4437a3e4f92e0722a0f".to_string(),
             build_dir: ["tree-sitter-javascript", "src"].iter().collect(),
             files: vec!["parser.c".to_string(), "scanner.c".to_string()],
         },
         TreeSitterProject {
             name: "tree-sitter-json".to_string(),
+            library: "tree-sitter-json".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-json.git".to_string(),
+            revision: "ee35a6ebefcef0c5c416c0d1ccec7370cfca5a24".to_string(),
             build_dir: ["tree-sitter-json", "src"].iter().collect(),
             files: vec!["parser.c".to_string()],
         },
+        TreeSitterProject {
+            name: "tree-sitter-php".to_string(),
+            library: "tree-sitter-php".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-php.git".to_string(),
+            revision: "v0.23.0".to_string(),
+            build_dir: ["tree-sitter-php", "php", "src"].iter().collect(),
+            files: vec!["parser.c".to_string(), "scanner.c".to_string()],
+        },
         TreeSitterProject {
             name: "tree-sitter-python".to_string(),
+            library: "tree-sitter-python".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-python.git".to_string(),
+            revision: "c01fb4e38587e959b9058b8cd34b9e6a3068c827".to_string(),
             build_dir: ["tree-sitter-python", "src"].iter().collect(),
             files: vec!["parser.c".to_string(), "scanner.c".to_string()],
         },
+        TreeSitterProject {
+            name: "tree-sitter-ruby".to_string(),
+            library: "tree-sitter-ruby".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-ruby.git".to_string(),
+            revision: "71bd32fb7607035768799732addba884a37a6210".to_string(),
+            build_dir: ["tree-sitter-ruby", "src"].iter().collect(),
+            files: vec!["parser.c".to_string(), "scanner.c".to_string()],
+        },
         TreeSitterProject {
             name: "tree-sitter-rust".to_string(),
+            library: "tree-sitter-rust".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-rust.git".to_string(),
+            revision: "afb6000a71fb9dff3f47f90d412ec080ae12bbb4".to_string(),
             build_dir: ["tree-sitter-rust", "src"].iter().collect(),
             files: vec!["parser.c".to_string(), "scanner.c".to_string()],
         },
         TreeSitterProject {
             name: "tree-sitter-typescript".to_string(),
+            library: "tree-sitter-typescript".to_string(),
             repository: "https://github.com/tree-sitter/tree-sitter-typescript.git".to_string(),
+            revision: "7db8390a16a2cae317f4f4423a7b642cd4cad8c9".to_string(),
+            build_dir: ["tree-sitter-typescript", "typescript", "src"]
+                .iter()
+                .collect(),
+            files: vec!["parser.c".to_string(), "scanner.c".to_string()],
+        },
+        // the tsx grammar lives in the same repository as the typescript one
+        TreeSitterProject {
+            name: "tree-sitter-typescript".to_string(),
+            library: "tree-sitter-tsx".to_string(),
+            repository: "https://github.com/tree-sitter/tree-sitter-typescript.git".to_string(),
+            revision: "7db8390a16a2cae317f4f4423a7b642cd4cad8c9".to_string(),
             build_dir: ["tree-sitter-typescript", "tsx", "src"].iter().collect(),
             files: vec!["parser.c".to_string(), "scanner.c".to_string()],
         },
     ];
 
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={GRAMMARS_DIR_ENV}");
+    let grammars_dir = env::var_os(GRAMMARS_DIR_ENV).map(PathBuf::from);
+
     // for each project
-    //  1. Check if already clone. It not, clone it
+    //  1. If we use a local directory, check the project is there. Otherwise,
+    //     clone the project (if needed) and checkout the pinned revision.
     //  2. Build the project
+    //  3. Record the revision we built to expose it at runtime
+    let mut grammars: Vec<(String, String)> = vec![];
     for tree_sitter_project in tree_sitter_projects {
-        if !Path::new(tree_sitter_project.name.as_str()).exists() {
-            run("git", |command| {
-                command
-                    .arg("clone")
-                    .arg(tree_sitter_project.repository.as_str())
-                    .arg(tree_sitter_project.name.as_str())
-            });
+        let root = grammars_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let project_dir = root.join(tree_sitter_project.name.as_str());
+
+        if grammars_dir.is_some() {
+            assert!(
+                project_dir.is_dir(),
+                "grammar {} not found in {}",
+                tree_sitter_project.name,
+                root.display()
+            );
+            println!("cargo:rerun-if-changed={}", project_dir.display());
+        } else {
+            checkout_project(&tree_sitter_project, &project_dir);
+        }
+
+        compile_project(
+            &tree_sitter_project,
+            &root.join(&tree_sitter_project.build_dir),
+        );
+
+        let revision = get_project_revision(&project_dir, tree_sitter_project.name.as_str());
+        if project_dir.join(".git").exists() {
+            // local checkouts may be at another revision on purpose (e.g. to test a grammar)
+            let pinned = git_rev_parse(&project_dir, tree_sitter_project.revision.as_str());
+            if pinned.as_ref() != Some(&revision) {
+                println!(
+                    "cargo:warning={} is at revision {} instead of {}",
+                    tree_sitter_project.name, revision, tree_sitter_project.revision
+                );
+            }
+        } else {
+            assert_eq!(
+                revision, tree_sitter_project.revision,
+                "vendored sources of {} are not at the pinned revision",
+                tree_sitter_project.name
+            );
         }
-        compile_project(&tree_sitter_project);
+        grammars.push((tree_sitter_project.library.clone(), revision));
     }
+
+    let grammars_code: String = grammars
+        .iter()
+        .map(|(name, revision)| format!("    (\"{name}\", \"{revision}\"),\n"))
+        .collect();
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR not defined"));
+    fs::write(
+        out_dir.join("tree_sitter_grammars.rs"),
+        format!("pub const TREE_SITTER_GRAMMARS: &[(&str, &str)] = &[\n{grammars_code}];\n"),
+    )
+    .expect("cannot write the grammars table");
 }
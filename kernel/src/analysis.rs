@@ -1,3 +1,4 @@
 pub mod analyze;
+pub mod dynamic_grammars;
 pub mod javascript;
 pub mod tree_sitter;
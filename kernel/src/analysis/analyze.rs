@@ -1,3 +1,4 @@
+use crate::analysis::dynamic_grammars::get_dynamic_grammar;
 use crate::analysis::javascript::execute_rule;
 use crate::analysis::tree_sitter::{get_query, get_query_nodes, get_tree};
 use crate::model::analysis::{AnalysisOptions, ERROR_INVALID_QUERY};
@@ -8,19 +9,33 @@ use std::collections::HashMap;
 fn get_lines_to_ignore(code: &str, language: &Language) -> Vec<u32> {
     let mut lines_to_ignore = vec![];
     let mut line_number = 1u32;
+    // dynamic languages define their own comment prefixes
+    let dynamic_patterns: Vec<String> = match language {
+        Language::Dynamic(name) => get_dynamic_grammar(name)
+            .map(|g| g.comment_prefixes)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| format!("{p}no-dd-sa"))
+            .collect(),
+        _ => vec![],
+    };
     let disabling_patterns = match language {
-        Language::Python | Language::Dockerfile => {
+        Language::Python | Language::Dockerfile | Language::Ruby | Language::Bash => {
             vec!["#no-dd-sa"]
         }
-        Language::JavaScript | Language::TypeScript => {
+        Language::JavaScript | Language::TypeScript | Language::C | Language::Cpp => {
             vec!["//no-dd-sa", "/*no-dd-sa*/"]
         }
         Language::Go | Language::Rust | Language::Csharp | Language::Java => {
             vec!["//no-dd-sa", "//no:dd-sa"]
         }
+        Language::Php => {
+            vec!["//no-dd-sa", "#no-dd-sa", "/*no-dd-sa*/"]
+        }
         Language::Json => {
             vec!["impossiblestringtoreach"]
         }
+        Language::Dynamic(_) => dynamic_patterns.iter().map(String::as_str).collect(),
     };
 
     for line in code.lines() {
@@ -50,7 +65,7 @@ pub fn analyze(
 ) -> Vec<RuleResult> {
     let lines_to_ignore = get_lines_to_ignore(code, language);
 
-    get_tree(code, language).map_or_else(
+    get_tree(code, language, filename).map_or_else(
         || {
             if analysis_option.use_debug {
                 eprintln!("error when parsing source file {filename}");
@@ -76,7 +91,8 @@ pub fn analyze(
                     }
 
                     if let Some(tree_sitter_query) = &rule.tree_sitter_query {
-                        let query_try = get_query(tree_sitter_query.as_str(), &rule.language);
+                        let query_try =
+                            get_query(tree_sitter_query.as_str(), &rule.language, filename);
 
                         match query_try {
                             Ok(query) => {
@@ -108,6 +124,14 @@ pub fn analyze(
                                         .cloned()
                                         .filter(|v| !lines_to_ignore.contains(&v.start.line))
                                         .collect();
+                                    for violation in &mut rule_result.violations {
+                                        violation.fingerprint =
+                                            Some(violation.compute_fingerprint(
+                                                rule_result.rule_name.as_str(),
+                                                filename,
+                                                code,
+                                            ));
+                                    }
                                     rule_result
                                 }
                             }
@@ -368,6 +392,12 @@ for(var i = 0; i <= 10; i--){}
             result1.violations.get(0).unwrap().message,
             "do not use less than".to_string()
         );
+        // the fingerprint of the violation is computed from the analyzed code
+        let violation = result1.violations.get(0).unwrap();
+        assert_eq!(
+            violation.fingerprint,
+            Some(violation.compute_fingerprint("myrule", "myfile.js", js_code))
+        );
     }
 
     // test showing violation ignore
@@ -424,6 +454,21 @@ def foo(arg1):
         assert!(result.violations.is_empty());
     }
 
+    // PHP accepts both shell-like and C-like comments to ignore a violation
+    #[test]
+    fn test_get_lines_to_ignore_php() {
+        let c = r#"<?php
+# no-dd-sa
+foo();
+// no-dd-sa
+bar();
+/* no-dd-sa */
+baz();
+qux();
+"#;
+        assert_eq!(vec![3, 5, 7], get_lines_to_ignore(c, &Language::Php));
+    }
+
     // test what happens when there is no tree-sitter
     #[test]
     fn test_execution_invalid_query() {
@@ -0,0 +1,281 @@
+use crate::model::common::{Language, ALL_LANGUAGES};
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use libloading::{Library, Symbol};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+// extension of the manifests that describe the grammars in the grammars directory.
+const MANIFEST_EXTENSION: &str = "json";
+
+/// Manifest of a grammar loaded at runtime. Manifests are JSON files located in the
+/// grammars directory, next to the shared library of the grammar.
+///
+/// ```json
+/// {
+///   "name": "KOTLIN",
+///   "library": "libtree-sitter-kotlin.so",
+///   "function": "tree_sitter_kotlin",
+///   "file_patterns": ["*.kt", "*.kts"],
+///   "comment_prefixes": ["//"]
+/// }
+/// ```
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct DynamicGrammarManifest {
+    // name of the language, as used by the rulesets
+    pub name: String,
+    // path of the shared library, relative to the manifest
+    pub library: String,
+    // function that returns the grammar, tree_sitter_<name> by default
+    pub function: Option<String>,
+    // glob patterns matched against the name of the files (e.g. *.kt)
+    #[serde(default)]
+    pub file_patterns: Vec<String>,
+    // prefixes of the comments used to ignore violations (e.g. //no-dd-sa)
+    #[serde(default)]
+    pub comment_prefixes: Vec<String>,
+}
+
+/// A grammar loaded at runtime and registered under a language name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DynamicGrammar {
+    pub name: &'static str,
+    pub file_patterns: Vec<String>,
+    pub comment_prefixes: Vec<String>,
+}
+
+struct LoadedGrammar {
+    grammar: DynamicGrammar,
+    tree_sitter_language: tree_sitter::Language,
+    // keep the library loaded as long as the grammar is used (i.e. forever)
+    _library: Library,
+}
+
+lazy_static! {
+    static ref DYNAMIC_GRAMMARS: RwLock<Vec<LoadedGrammar>> = RwLock::new(vec![]);
+}
+
+/// Load a grammar from its manifest and register it. Returns the language of the grammar.
+pub fn load_dynamic_grammar(manifest_path: &Path) -> Result<Language> {
+    let manifest_content = fs::read_to_string(manifest_path)
+        .with_context(|| format!("cannot read manifest {}", manifest_path.display()))?;
+    let manifest: DynamicGrammarManifest = serde_json::from_str(manifest_content.as_str())
+        .with_context(|| format!("invalid manifest {}", manifest_path.display()))?;
+
+    let name = manifest.name.to_uppercase();
+    if ALL_LANGUAGES.iter().any(|l| l.serde_name() == name) {
+        return Err(anyhow!("grammar {} is already built in", name));
+    }
+    if get_dynamic_grammar(name.as_str()).is_some() {
+        return Err(anyhow!("grammar {} is already loaded", name));
+    }
+
+    let library_path = manifest_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(manifest.library.as_str());
+    let function = manifest
+        .function
+        .unwrap_or_else(|| format!("tree_sitter_{}", name.to_lowercase()));
+
+    // Loading a library runs its initialization code: grammars must come from a trusted directory.
+    let (library, tree_sitter_language) = unsafe {
+        let library = Library::new(&library_path)
+            .with_context(|| format!("cannot load library {}", library_path.display()))?;
+        let language_function: Symbol<unsafe extern "C" fn() -> tree_sitter::Language> = library
+            .get(function.as_bytes())
+            .with_context(|| format!("function {} not found", function))?;
+        let tree_sitter_language = language_function();
+        (library, tree_sitter_language)
+    };
+
+    // check the grammar ABI is compatible with our version of tree-sitter
+    tree_sitter::Parser::new()
+        .set_language(tree_sitter_language)
+        .with_context(|| format!("grammar {} is not compatible", name))?;
+
+    let grammar = DynamicGrammar {
+        name: Box::leak(name.into_boxed_str()),
+        file_patterns: manifest.file_patterns,
+        comment_prefixes: manifest.comment_prefixes,
+    };
+    let language = Language::Dynamic(grammar.name);
+    DYNAMIC_GRAMMARS.write().unwrap().push(LoadedGrammar {
+        grammar,
+        tree_sitter_language,
+        _library: library,
+    });
+    Ok(language)
+}
+
+/// Load all the grammars from the manifests of a directory.
+pub fn load_dynamic_grammars(directory: &Path) -> Result<Vec<Language>> {
+    let mut manifests = fs::read_dir(directory)
+        .with_context(|| format!("cannot read grammars directory {}", directory.display()))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(MANIFEST_EXTENSION))
+        .collect::<Vec<_>>();
+    // always load the grammars in the same order
+    manifests.sort();
+    manifests.iter().map(|m| load_dynamic_grammar(m)).collect()
+}
+
+/// Get all the grammars loaded at runtime.
+pub fn get_dynamic_grammars() -> Vec<DynamicGrammar> {
+    DYNAMIC_GRAMMARS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|g| g.grammar.clone())
+        .collect()
+}
+
+/// Get a grammar loaded at runtime from its language name.
+pub fn get_dynamic_grammar(name: &str) -> Option<DynamicGrammar> {
+    DYNAMIC_GRAMMARS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|g| g.grammar.name == name)
+        .map(|g| g.grammar.clone())
+}
+
+pub(crate) fn get_dynamic_tree_sitter_language(name: &str) -> Option<tree_sitter::Language> {
+    DYNAMIC_GRAMMARS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|g| g.grammar.name == name)
+        .map(|g| g.tree_sitter_language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::analyze::analyze;
+    use crate::model::analysis::AnalysisOptions;
+    use crate::model::rule::{RuleCategory, RuleInternal, RuleSeverity};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    // write a manifest in a temporary directory, removed when the directory is dropped
+    fn write_manifest(content: &str) -> (TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("grammar.json");
+        fs::write(&path, content).unwrap();
+        (temp_dir, path)
+    }
+
+    #[test]
+    fn test_load_dynamic_grammar_builtin_name() {
+        let (_temp_dir, manifest) =
+            write_manifest(r#"{"name": "python", "library": "libtree-sitter-python.so"}"#);
+        let err = load_dynamic_grammar(&manifest).unwrap_err();
+        assert_eq!("grammar PYTHON is already built in", err.to_string());
+    }
+
+    #[test]
+    fn test_load_dynamic_grammar_missing_library() {
+        let (_temp_dir, manifest) = write_manifest(
+            r#"{"name": "MISSING", "library": "does-not-exist.so", "file_patterns": ["*.missing"]}"#,
+        );
+        assert!(load_dynamic_grammar(&manifest).is_err());
+        assert!(get_dynamic_grammar("MISSING").is_none());
+    }
+
+    #[test]
+    fn test_load_dynamic_grammar_invalid_manifest() {
+        let (_temp_dir, manifest) = write_manifest(r#"{"library": "foo.so"}"#);
+        assert!(load_dynamic_grammar(&manifest).is_err());
+    }
+
+    // Build the Go grammar the kernel is built with as a shared library, load it from a
+    // grammars directory under another name and analyze a file with it. The grammar is
+    // compiled with the C compiler used to build the kernel (CC or cc).
+    #[cfg(unix)]
+    #[test]
+    fn test_load_dynamic_grammars_and_analyze() {
+        let sources = option_env!("DD_TREE_SITTER_GRAMMARS_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+            .join("tree-sitter-go")
+            .join("src");
+        let grammars_directory = tempfile::tempdir().unwrap();
+        let library = format!(
+            "{}tree-sitter-testgo{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        );
+        let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+            .args(["-shared", "-fPIC", "-w", "-I"])
+            .arg(&sources)
+            .arg(sources.join("parser.c"))
+            .arg("-o")
+            .arg(grammars_directory.path().join(&library))
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::write(
+            grammars_directory.path().join("testgo.json"),
+            format!(
+                r#"{{"name": "testgo", "library": "{}", "function": "tree_sitter_go", "file_patterns": ["*.go"], "comment_prefixes": ["//"]}}"#,
+                library
+            ),
+        )
+        .unwrap();
+
+        let languages = load_dynamic_grammars(grammars_directory.path()).unwrap();
+        assert_eq!(vec![Language::Dynamic("TESTGO")], languages);
+        assert_eq!(
+            Some(vec!["*.go".to_string()]),
+            get_dynamic_grammar("TESTGO").map(|g| g.file_patterns)
+        );
+
+        let rule = RuleInternal {
+            name: "myrule".to_string(),
+            short_description: None,
+            description: None,
+            category: RuleCategory::CodeStyle,
+            severity: RuleSeverity::Notice,
+            language: languages[0],
+            code: r#"
+function visit(node, filename, code) {
+    const functionName = node.captures["name"];
+    if(functionName) {
+        const error = buildError(functionName.start.line, functionName.start.col, functionName.end.line, functionName.end.col,
+                                 "invalid name", "CRITICAL", "security");
+        addError(error);
+    }
+}
+"#
+            .to_string(),
+            tree_sitter_query: Some("(function_declaration name: (identifier) @name)".to_string()),
+            variables: HashMap::new(),
+        };
+        // the function after the //no-dd-sa comment is not reported
+        let results = analyze(
+            &languages[0],
+            vec![rule],
+            "main.go",
+            "package main\n\nfunc foo() {}\n\n//no-dd-sa\nfunc bar() {}\n",
+            &AnalysisOptions {
+                log_output: true,
+                use_debug: false,
+            },
+        );
+        assert_eq!(1, results.len());
+        assert!(results[0].errors.is_empty());
+        assert_eq!(
+            vec![3],
+            results[0]
+                .violations
+                .iter()
+                .map(|v| v.start.line)
+                .collect::<Vec<u32>>()
+        );
+    }
+}
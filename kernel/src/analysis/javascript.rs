@@ -263,6 +263,7 @@ res
                                     category: rule.category,
                                     severity: rule.severity,
                                     fixes: v.fixes,
+                                    fingerprint: None,
                                 })
                                 .collect();
                             RuleResult {
@@ -323,6 +324,24 @@ res
     }
 }
 
+// check the syntax of the JavaScript code of a rule. The code is compiled as the body
+// of a function that is never called so that the code of the rule is not executed.
+pub fn validate_javascript(code: &str) -> anyhow::Result<()> {
+    let js_code = format!("new Function({});", serde_json::to_string(code)?);
+    if js_code.len() >= v8::String::max_length() {
+        return Err(anyhow::anyhow!(ERROR_RULE_CODE_TOO_BIG));
+    }
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    runtime
+        .execute_script("validate_javascript", FastString::from(js_code))
+        .map(|_| ())
+        .map_err(|e| {
+            // only keep the message, the stack trace is about the validation code
+            let message = e.to_string();
+            anyhow::anyhow!(message.lines().next().unwrap_or_default().to_string())
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -352,8 +371,8 @@ mod tests {
     def __init__(self):
         pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -406,8 +425,8 @@ function visit(node, filename, code) {
 def foo(arg1):
     pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -469,7 +488,7 @@ function visit(node, filename, code) {
 def foo(arg1):
     pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -481,7 +500,7 @@ def foo(arg1):
             tree_sitter_query: Some(q.to_string()),
             variables: HashMap::new(),
         };
-        let query = get_query(q, &Language::Python).unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let nodes = get_query_nodes(&tree, &query, "plop", c, &HashMap::new());
 
         let rule_execution = execute_rule(
@@ -529,8 +548,8 @@ function visit(node, filename, code) {
 def foo(arg1):
     pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -588,8 +607,8 @@ function visit(node, filename, code) {
 def foo(arg1):
     pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -641,8 +660,8 @@ function visit(node, filena
 def foo(arg1):
     pass
         "#;
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).unwrap();
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").unwrap();
         let rule = RuleInternal {
             name: "myrule".to_string(),
             short_description: Some("short desc".to_string()),
@@ -682,4 +701,13 @@ def foo(arg1):
             rule_execution.errors.get(0).unwrap()
         )
     }
+
+    #[test]
+    fn test_validate_javascript() {
+        assert!(validate_javascript("function visit(node, filename, code) {}").is_ok());
+        // the code is not executed
+        assert!(validate_javascript("while(true) {}").is_ok());
+        let err = validate_javascript("function visit(node, filename, code) {").unwrap_err();
+        assert!(err.to_string().contains("SyntaxError"));
+    }
 }
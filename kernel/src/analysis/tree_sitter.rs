@@ -1,52 +1,154 @@
+use crate::analysis::dynamic_grammars::get_dynamic_tree_sitter_language;
 use crate::model::analysis::{MatchNode, MatchNodeContext, TreeSitterNode};
 use crate::model::common::{Language, Position};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::path::Path;
 use tree_sitter::QueryCursor;
 
-fn get_tree_sitter_language(language: &Language) -> tree_sitter::Language {
-    extern "C" {
-        fn tree_sitter_c_sharp() -> tree_sitter::Language;
-        fn tree_sitter_dockerfile() -> tree_sitter::Language;
-        fn tree_sitter_go() -> tree_sitter::Language;
-        fn tree_sitter_java() -> tree_sitter::Language;
-        fn tree_sitter_javascript() -> tree_sitter::Language;
-        fn tree_sitter_json() -> tree_sitter::Language;
-        fn tree_sitter_python() -> tree_sitter::Language;
-        fn tree_sitter_rust() -> tree_sitter::Language;
-        fn tree_sitter_tsx() -> tree_sitter::Language;
-        // fn tree_sitter_yaml() -> tree_sitter::Language;
+extern "C" {
+    fn tree_sitter_bash() -> tree_sitter::Language;
+    fn tree_sitter_c() -> tree_sitter::Language;
+    fn tree_sitter_cpp() -> tree_sitter::Language;
+    fn tree_sitter_c_sharp() -> tree_sitter::Language;
+    fn tree_sitter_dockerfile() -> tree_sitter::Language;
+    fn tree_sitter_go() -> tree_sitter::Language;
+    fn tree_sitter_java() -> tree_sitter::Language;
+    fn tree_sitter_javascript() -> tree_sitter::Language;
+    fn tree_sitter_json() -> tree_sitter::Language;
+    fn tree_sitter_php() -> tree_sitter::Language;
+    fn tree_sitter_python() -> tree_sitter::Language;
+    fn tree_sitter_ruby() -> tree_sitter::Language;
+    fn tree_sitter_rust() -> tree_sitter::Language;
+    fn tree_sitter_tsx() -> tree_sitter::Language;
+    fn tree_sitter_typescript() -> tree_sitter::Language;
+    // fn tree_sitter_yaml() -> tree_sitter::Language;
+}
 
-    }
+// extensions of TypeScript files that do not support JSX and are parsed with the
+// typescript grammar. All other TypeScript files (.tsx, unknown) use the tsx grammar.
+const TYPESCRIPT_EXTENSIONS: &[&str] = &["ts", "mts", "cts"];
+
+// TypeScript files without JSX, parsed with the typescript grammar
+fn is_typescript_file(filename: &str) -> bool {
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    TYPESCRIPT_EXTENSIONS.contains(&extension)
+}
 
-    match language {
+// get the grammar to use for a file. All languages have a single grammar except
+// TypeScript: the typescript grammar rejects JSX and the tsx grammar rejects
+// type assertions (`<Foo>bar`), so we pick the grammar from the file extension.
+// Returns None for a dynamic language whose grammar is not loaded.
+fn get_tree_sitter_language(language: &Language, filename: &str) -> Option<tree_sitter::Language> {
+    let tree_sitter_language = match language {
+        Language::Bash => unsafe { tree_sitter_bash() },
+        Language::C => unsafe { tree_sitter_c() },
+        Language::Cpp => unsafe { tree_sitter_cpp() },
         Language::Csharp => unsafe { tree_sitter_c_sharp() },
         Language::Dockerfile => unsafe { tree_sitter_dockerfile() },
         Language::Go => unsafe { tree_sitter_go() },
         Language::Java => unsafe { tree_sitter_java() },
         Language::JavaScript => unsafe { tree_sitter_javascript() },
         Language::Json => unsafe { tree_sitter_json() },
+        Language::Php => unsafe { tree_sitter_php() },
         Language::Python => unsafe { tree_sitter_python() },
+        Language::Ruby => unsafe { tree_sitter_ruby() },
         Language::Rust => unsafe { tree_sitter_rust() },
+        Language::TypeScript if is_typescript_file(filename) => unsafe { tree_sitter_typescript() },
         Language::TypeScript => unsafe { tree_sitter_tsx() },
-        // Language::Yaml => unsafe { tree_sitter_yaml() },
+        Language::Dynamic(name) => return get_dynamic_tree_sitter_language(name),
+    };
+    Some(tree_sitter_language)
+}
+
+/// Name of the grammar used to parse a file: the name of the language, except for
+/// TypeScript that has two grammars (typescript and tsx).
+pub fn get_grammar_name(language: &Language, filename: &str) -> String {
+    match language {
+        Language::TypeScript if is_typescript_file(filename) => "typescript".to_string(),
+        Language::TypeScript => "tsx".to_string(),
+        _ => language.to_string(),
+    }
+}
+
+// get all the grammars that may be used to parse files of a language, with their name.
+fn get_tree_sitter_languages(language: &Language) -> Vec<(String, tree_sitter::Language)> {
+    match language {
+        Language::TypeScript => unsafe {
+            vec![
+                ("typescript".to_string(), tree_sitter_typescript()),
+                ("tsx".to_string(), tree_sitter_tsx()),
+            ]
+        },
+        _ => get_tree_sitter_language(language, "")
+            .map(|l| (get_grammar_name(language, ""), l))
+            .into_iter()
+            .collect(),
     }
 }
 
-// get the tree-sitter tree
-pub fn get_tree(code: &str, language: &Language) -> Option<tree_sitter::Tree> {
+// get the tree-sitter tree. The filename is used to select the grammar.
+pub fn get_tree(code: &str, language: &Language, filename: &str) -> Option<tree_sitter::Tree> {
     let mut tree_sitter_parser = tree_sitter::Parser::new();
-    let tree_sitter_language = get_tree_sitter_language(language);
+    let tree_sitter_language = get_tree_sitter_language(language, filename)?;
     tree_sitter_parser.set_language(tree_sitter_language).ok()?;
     tree_sitter_parser.parse(code, None)
 }
 
-// build the query from tree-sitter
-pub fn get_query(query_code: &str, language: &Language) -> Result<tree_sitter::Query> {
-    let tree_sitter_language = get_tree_sitter_language(language);
+// build the query from tree-sitter for the grammar used to parse the file.
+pub fn get_query(
+    query_code: &str,
+    language: &Language,
+    filename: &str,
+) -> Result<tree_sitter::Query> {
+    let tree_sitter_language = get_tree_sitter_language(language, filename)
+        .ok_or_else(|| anyhow!("no grammar loaded for language {}", language))?;
     Ok(tree_sitter::Query::new(tree_sitter_language, query_code)?)
 }
 
+/// Compile a query with all the grammars of a language: the query or the error of each
+/// grammar, by grammar name. Empty if the grammar of the language is not loaded.
+pub fn check_query(
+    query_code: &str,
+    language: &Language,
+) -> Vec<(String, Result<tree_sitter::Query>)> {
+    get_tree_sitter_languages(language)
+        .into_iter()
+        .map(|(name, tree_sitter_language)| {
+            let query = tree_sitter::Query::new(tree_sitter_language, query_code)
+                .map_err(anyhow::Error::from);
+            (name, query)
+        })
+        .collect()
+}
+
+/// Check that a query is valid for at least one grammar of a language and return the
+/// names of the grammars it is valid for. This is used when loading rules: a TypeScript
+/// rule that only compiles with the tsx grammar (e.g. a JSX query) is only executed on
+/// the files parsed with the tsx grammar (see `get_grammar_name`).
+pub fn validate_query(query_code: &str, language: &Language) -> Result<Vec<String>> {
+    let results = check_query(query_code, language);
+    if results.is_empty() {
+        return Err(anyhow!("no grammar loaded for language {}", language));
+    }
+    let valid_grammars: Vec<String> = results
+        .iter()
+        .filter(|(_, query)| query.is_ok())
+        .map(|(name, _)| name.clone())
+        .collect();
+    if valid_grammars.is_empty() {
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|(name, query)| query.as_ref().err().map(|e| format!("{}: {}", name, e)))
+            .collect();
+        return Err(anyhow!("{}", errors.join(", ")));
+    }
+    Ok(valid_grammars)
+}
+
 // Get all the match nodes based on a query. For each match, we build a `MatchNode`
 // object. This object is deserialized and this is what is passed to the visit function.
 // This is the first argument of the visit function.
@@ -165,7 +267,7 @@ arr = ["foo", "bar"];
 
 def func():
    pass;"#;
-        let t = get_tree(source_code, &Language::Python);
+        let t = get_tree(source_code, &Language::Python, "myfile.py");
         assert!(t.is_some());
         assert_eq!("module", t.unwrap().root_node().kind());
     }
@@ -177,7 +279,7 @@ arr = ["foo", "bar"];
 
 def func():
    pass;"#;
-        let t = get_tree(source_code, &Language::Python);
+        let t = get_tree(source_code, &Language::Python, "myfile.py");
         assert!(t.is_some());
         let tree_node = map_node(t.unwrap().root_node());
         assert!(tree_node.is_some());
@@ -205,6 +307,48 @@ def func():
         );
     }
 
+    #[test]
+    fn test_bash_get_tree() {
+        let source_code = r#"#!/bin/bash
+for f in *.txt; do
+    echo "$f"
+done
+"#;
+        let t = get_tree(source_code, &Language::Bash, "myfile.sh");
+        assert!(t.is_some());
+        assert_eq!("program", t.unwrap().root_node().kind());
+    }
+
+    #[test]
+    fn test_c_get_tree() {
+        let source_code = r#"
+#include <stdio.h>
+
+int main(int argc, char **argv) {
+    printf("hello world");
+    return 0;
+}
+"#;
+        let t = get_tree(source_code, &Language::C, "myfile.c");
+        assert!(t.is_some());
+        assert_eq!("translation_unit", t.unwrap().root_node().kind());
+    }
+
+    #[test]
+    fn test_cpp_get_tree() {
+        let source_code = r#"
+#include <iostream>
+
+class Foo {
+public:
+    void bar() { std::cout << "bar" << std::endl; }
+};
+"#;
+        let t = get_tree(source_code, &Language::Cpp, "myfile.cpp");
+        assert!(t.is_some());
+        assert_eq!("translation_unit", t.unwrap().root_node().kind());
+    }
+
     #[test]
     fn test_csharp_get_tree() {
         let source_code = r#"
@@ -218,7 +362,7 @@ namespace HelloWorld
     }
 }
 "#;
-        let t = get_tree(source_code, &Language::Csharp);
+        let t = get_tree(source_code, &Language::Csharp, "myfile.cs");
         assert!(t.is_some());
         assert_eq!("compilation_unit", t.unwrap().root_node().kind());
     }
@@ -228,7 +372,7 @@ namespace HelloWorld
         let source_code = r#"
 RUN /blabla
 "#;
-        let t = get_tree(source_code, &Language::Dockerfile);
+        let t = get_tree(source_code, &Language::Dockerfile, "Dockerfile");
         assert!(t.is_some());
         assert_eq!("source_file", t.unwrap().root_node().kind());
     }
@@ -242,7 +386,7 @@ func main() {
     fmt.Println("hello world")
 }
 "#;
-        let t = get_tree(source_code, &Language::Go);
+        let t = get_tree(source_code, &Language::Go, "myfile.go");
         assert!(t.is_some());
         assert_eq!("source_file", t.unwrap().root_node().kind());
     }
@@ -253,7 +397,7 @@ func main() {
 class Foo {
 }
 "#;
-        let t = get_tree(source_code, &Language::Java);
+        let t = get_tree(source_code, &Language::Java, "myfile.java");
         assert!(t.is_some());
         assert_eq!("program", t.unwrap().root_node().kind());
     }
@@ -262,7 +406,7 @@ class Foo {
     fn test_javascript_get_tree() {
         let source_code = r#"
 function foo() {console.log("bar");}"#;
-        let t = get_tree(source_code, &Language::JavaScript);
+        let t = get_tree(source_code, &Language::JavaScript, "myfile.js");
         assert!(t.is_some());
         assert_eq!("program", t.unwrap().root_node().kind());
     }
@@ -271,11 +415,37 @@ function foo() {console.log("bar");}"#;
     fn test_json_get_tree() {
         let source_code = r#"
 {}"#;
-        let t = get_tree(source_code, &Language::Json);
+        let t = get_tree(source_code, &Language::Json, "myfile.json");
         assert!(t.is_some());
         assert_eq!("document", t.unwrap().root_node().kind());
     }
 
+    #[test]
+    fn test_php_get_tree() {
+        let source_code = r#"<?php
+function foo($bar) {
+    echo $bar;
+}
+"#;
+        let t = get_tree(source_code, &Language::Php, "myfile.php");
+        assert!(t.is_some());
+        assert_eq!("program", t.unwrap().root_node().kind());
+    }
+
+    #[test]
+    fn test_ruby_get_tree() {
+        let source_code = r#"
+class Foo
+  def bar(baz)
+    puts baz
+  end
+end
+"#;
+        let t = get_tree(source_code, &Language::Ruby, "myfile.rb");
+        assert!(t.is_some());
+        assert_eq!("program", t.unwrap().root_node().kind());
+    }
+
     #[test]
     fn test_rust_get_tree() {
         let source_code = r#"
@@ -283,7 +453,7 @@ fn foo(bar: String) -> String {
    return "foobar".to_string();
 }
 "#;
-        let t = get_tree(source_code, &Language::Rust);
+        let t = get_tree(source_code, &Language::Rust, "myfile.rs");
         assert!(t.is_some());
         assert_eq!("source_file", t.unwrap().root_node().kind());
     }
@@ -295,11 +465,71 @@ let myAdd = function (x: number, y: number): number {
   return x + y;
 };
 "#;
-        let t = get_tree(source_code, &Language::TypeScript);
+        let t = get_tree(source_code, &Language::TypeScript, "myfile.ts");
         assert!(t.is_some());
         assert_eq!("program", t.unwrap().root_node().kind());
     }
 
+    // type assertions are only valid with the typescript grammar
+    #[test]
+    fn test_typescript_type_assertion_get_tree() {
+        let source_code = "let foo = <string>bar;";
+        let t = get_tree(source_code, &Language::TypeScript, "myfile.ts").unwrap();
+        assert!(!t.root_node().has_error());
+        let t = get_tree(source_code, &Language::TypeScript, "myfile.tsx").unwrap();
+        assert!(t.root_node().has_error());
+    }
+
+    // JSX is only valid with the tsx grammar
+    #[test]
+    fn test_tsx_get_tree() {
+        let source_code = "const foo = <div className=\"bar\">baz</div>;";
+        let t = get_tree(source_code, &Language::TypeScript, "myfile.tsx").unwrap();
+        assert!(!t.root_node().has_error());
+        let t = get_tree(source_code, &Language::TypeScript, "myfile.ts").unwrap();
+        assert!(t.root_node().has_error());
+    }
+
+    // queries must compile with at least one grammar of the language
+    #[test]
+    fn test_get_grammar_name() {
+        assert_eq!(
+            "typescript",
+            get_grammar_name(&Language::TypeScript, "src/file.ts")
+        );
+        assert_eq!(
+            "tsx",
+            get_grammar_name(&Language::TypeScript, "src/file.tsx")
+        );
+        assert_eq!("python", get_grammar_name(&Language::Python, "src/file.py"));
+    }
+
+    #[test]
+    fn test_validate_query() {
+        assert_eq!(
+            vec!["typescript".to_string(), "tsx".to_string()],
+            validate_query("(identifier) @id", &Language::TypeScript).unwrap()
+        );
+        assert_eq!(
+            vec!["tsx".to_string()],
+            validate_query("(jsx_element) @jsx", &Language::TypeScript).unwrap()
+        );
+        assert!(get_query("(jsx_element) @jsx", &Language::TypeScript, "myfile.tsx").is_ok());
+        assert!(get_query("(jsx_element) @jsx", &Language::TypeScript, "myfile.ts").is_err());
+        assert!(validate_query("(not_a_node) @n", &Language::TypeScript).is_err());
+        assert!(validate_query("(function_definition) @f", &Language::Python).is_ok());
+        assert!(validate_query("(jsx_element) @jsx", &Language::Python).is_err());
+    }
+
+    // dynamic languages cannot be used until their grammar is loaded
+    #[test]
+    fn test_dynamic_language_not_loaded() {
+        let language = Language::Dynamic("NOTLOADED");
+        assert!(get_tree("foo", &language, "myfile.foo").is_none());
+        assert!(get_query("(identifier) @id", &language, "myfile.foo").is_err());
+        assert!(validate_query("(identifier) @id", &language).is_err());
+    }
+
     //     #[test]
     //     fn test_yaml_get_tree() {
     //         let source_code = r#"
@@ -309,7 +539,7 @@ let myAdd = function (x: number, y: number): number {
     //   - python-inclusive
     //   - python-security
     // "#;
-    //         let t = get_tree(source_code, &Language::Yaml);
+    //         let t = get_tree(source_code, &Language::Yaml, "myfile.yaml");
     //         assert!(t.is_some());
     //         assert_eq!("stream", t.unwrap().root_node().kind());
     //     }
@@ -332,8 +562,8 @@ let myAdd = function (x: number, y: number): number {
         pass
         "#;
 
-        let tree = get_tree(c, &Language::Python).unwrap();
-        let query = get_query(q, &Language::Python).expect("query defined");
+        let tree = get_tree(c, &Language::Python, "myfile.py").unwrap();
+        let query = get_query(q, &Language::Python, "myfile.py").expect("query defined");
         let query_nodes = get_query_nodes(&tree, &query, "myfile.py", c, &HashMap::new());
         assert_eq!(query_nodes.len(), 1);
         let query_node = query_nodes.get(0).unwrap();
@@ -1,2 +1,5 @@
 pub const VERSION: &str = "development";
 pub const CARGO_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// name and revision of the tree-sitter grammars compiled in the kernel (generated by build.rs)
+include!(concat!(env!("OUT_DIR"), "/tree_sitter_grammars.rs"));
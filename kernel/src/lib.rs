@@ -1,4 +1,5 @@
 pub mod analysis;
 pub mod constants;
 pub mod model;
+pub mod signature_utils;
 pub mod utils;
@@ -3,4 +3,5 @@ pub mod common;
 pub mod rule;
 pub mod rule_test;
 pub mod ruleset;
+pub mod signature;
 pub mod violation;
@@ -1,5 +1,6 @@
+use crate::analysis::dynamic_grammars::get_dynamic_grammar;
 use derive_builder::Builder;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::fmt;
 
 #[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
@@ -20,62 +21,125 @@ impl fmt::Display for OutputFormat {
     }
 }
 
-#[derive(Copy, Clone, Deserialize, Debug, Serialize, Eq, Hash, PartialEq)]
+// Languages are serialized with their name in the API and rulesets (e.g. PYTHON).
+#[derive(Copy, Clone, Debug, Serialize, Eq, Hash, PartialEq)]
+#[serde(into = "String")]
 pub enum Language {
-    #[serde(rename = "CSHARP")]
+    Bash,
+    C,
+    Cpp,
     Csharp,
-    #[serde(rename = "DOCKERFILE")]
     Dockerfile,
-    #[serde(rename = "GO")]
     Go,
-    #[serde(rename = "JAVA")]
     Java,
-    #[serde(rename = "JAVASCRIPT")]
     JavaScript,
-    #[serde(rename = "JSON")]
     Json,
-    #[serde(rename = "PYTHON")]
+    Php,
     Python,
-    #[serde(rename = "RUST")]
+    Ruby,
     Rust,
-    #[serde(rename = "TYPESCRIPT")]
     TypeScript,
     // #[serde(rename = "YAML")]
     // Yaml,
+    /// Language of a grammar loaded at runtime, see `analysis::dynamic_grammars`.
+    Dynamic(&'static str),
 }
 
 #[allow(dead_code)]
 pub static ALL_LANGUAGES: &[Language] = &[
+    Language::Bash,
+    Language::C,
+    Language::Cpp,
     Language::Csharp,
     Language::Dockerfile,
     Language::Go,
     Language::Java,
     Language::JavaScript,
     Language::Json,
+    Language::Php,
     Language::Python,
+    Language::Ruby,
     Language::Rust,
     Language::TypeScript,
 ];
 
+impl Language {
+    /// Name of the language in the API and the rulesets.
+    pub fn serde_name(&self) -> &'static str {
+        match self {
+            Self::Bash => "BASH",
+            Self::C => "C",
+            Self::Cpp => "CPP",
+            Self::Csharp => "CSHARP",
+            Self::Dockerfile => "DOCKERFILE",
+            Self::Go => "GO",
+            Self::Java => "JAVA",
+            Self::JavaScript => "JAVASCRIPT",
+            Self::Json => "JSON",
+            Self::Php => "PHP",
+            Self::Python => "PYTHON",
+            Self::Ruby => "RUBY",
+            Self::Rust => "RUST",
+            Self::TypeScript => "TYPESCRIPT",
+            // Self::Yaml => "YAML",
+            Self::Dynamic(name) => name,
+        }
+    }
+}
+
+impl From<Language> for String {
+    fn from(language: Language) -> Self {
+        language.serde_name().to_string()
+    }
+}
+
+// Dynamic languages can only be deserialized once their grammar has been loaded.
+impl TryFrom<String> for Language {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        ALL_LANGUAGES
+            .iter()
+            .find(|l| l.serde_name() == value)
+            .copied()
+            .or_else(|| get_dynamic_grammar(value.as_str()).map(|g| Language::Dynamic(g.name)))
+            .ok_or_else(|| format!("unknown language {value}"))
+    }
+}
+
+// Implemented manually: the derived implementation would only deserialize 'static data
+// because of the name of the dynamic languages.
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Language::try_from(String::deserialize(deserializer)?).map_err(de::Error::custom)
+    }
+}
+
 impl fmt::Display for Language {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
+            Self::Bash => "bash",
+            Self::C => "c",
+            Self::Cpp => "c++",
             Self::Csharp => "c#",
             Self::Dockerfile => "dockerfile",
             Self::Go => "go",
             Self::Java => "java",
             Self::JavaScript => "javascript",
             Self::Json => "json",
+            Self::Php => "php",
             Self::Python => "python",
+            Self::Ruby => "ruby",
             Self::Rust => "rust",
             Self::TypeScript => "typescript",
             // Self::Yaml => "yaml",
+            Self::Dynamic(name) => return write!(f, "{}", name.to_lowercase()),
         };
         write!(f, "{s}")
     }
 }
 
-#[derive(Deserialize, Debug, Serialize, Clone, Builder)]
+#[derive(Deserialize, Debug, Serialize, Clone, Builder, Eq, PartialEq)]
 pub struct Position {
     pub line: u32,
     pub col: u32,
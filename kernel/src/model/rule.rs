@@ -3,12 +3,13 @@ use base64::engine::general_purpose;
 use base64::Engine;
 
 use crate::model::rule_test::RuleTest;
+use crate::model::signature::Signature;
 use crate::model::violation::Violation;
 use anyhow::anyhow;
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
 /// In the RuleCategory, we keep unknown. Old rules keep putting
@@ -136,6 +137,10 @@ pub struct Rule {
     pub tree_sitter_query_base64: Option<String>,
     pub variables: HashMap<String, String>,
     pub tests: Vec<RuleTest>,
+    // signature of the code of the rule, see `signature_utils`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub signature: Option<Signature>,
 }
 
 // This structure is used internally to handle rules.
@@ -220,6 +225,29 @@ impl Rule {
         hasher.update(self.code_base64.clone().as_bytes());
         format!("{:x}", hasher.finalize())
     }
+
+    /// Compute a checksum of everything that changes what the rule reports: its code,
+    /// tree-sitter query, language, severity, category and variables. Unlike
+    /// `compute_checksum`, a change of any of these changes the checksum.
+    pub fn compute_content_checksum(&self) -> String {
+        let variables: BTreeMap<&String, &String> = self.variables.iter().collect();
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(format!("code:{}\n", self.code_base64).as_bytes());
+        hasher.update(
+            format!(
+                "query:{}\n",
+                self.tree_sitter_query_base64.as_deref().unwrap_or_default()
+            )
+            .as_bytes(),
+        );
+        hasher.update(format!("language:{}\n", self.language).as_bytes());
+        hasher.update(format!("severity:{}\n", self.severity).as_bytes());
+        hasher.update(format!("category:{}\n", self.category).as_bytes());
+        for (name, value) in variables {
+            hasher.update(format!("variable:{}={}\n", name, value).as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
 }
 
 impl fmt::Display for Rule {
@@ -262,6 +290,7 @@ mod tests {
             tree_sitter_query_base64: None,
             variables: HashMap::new(),
             tests: vec![],
+            signature: None,
         };
         let rule_valid_checksum = Rule {
             name: "myrule".to_string(),
@@ -279,8 +308,51 @@ mod tests {
             tree_sitter_query_base64: None,
             variables: HashMap::new(),
             tests: vec![],
+            signature: None,
         };
         assert!(!rule_invalid_checksum.verify_checksum());
         assert!(rule_valid_checksum.verify_checksum());
     }
+
+    #[test]
+    fn test_content_checksum() {
+        let rule = Rule {
+            name: "myrule".to_string(),
+            short_description_base64: None,
+            description_base64: None,
+            category: RuleCategory::BestPractices,
+            severity: RuleSeverity::Warning,
+            language: Language::Python,
+            rule_type: RuleType::TreeSitterQuery,
+            entity_checked: None,
+            code_base64: encode_base64_string("rule code".to_string()),
+            checksum: "".to_string(),
+            pattern: None,
+            tree_sitter_query_base64: Some(encode_base64_string("(identifier)".to_string())),
+            variables: HashMap::from([
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "2".to_string()),
+            ]),
+            tests: vec![],
+            signature: None,
+        };
+        let checksum = rule.compute_content_checksum();
+        assert_eq!(checksum, rule.clone().compute_content_checksum());
+
+        // the description does not change what the rule reports
+        let mut same = rule.clone();
+        same.description_base64 = Some("bli".to_string());
+        assert_eq!(checksum, same.compute_content_checksum());
+
+        let mut changed = vec![rule.clone(), rule.clone(), rule.clone(), rule.clone()];
+        changed[0].tree_sitter_query_base64 = None;
+        changed[1].severity = RuleSeverity::Error;
+        changed[2].category = RuleCategory::Security;
+        changed[3]
+            .variables
+            .insert("a".to_string(), "3".to_string());
+        for rule in changed {
+            assert_ne!(checksum, rule.compute_content_checksum());
+        }
+    }
 }
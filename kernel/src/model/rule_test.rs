@@ -1,9 +1,28 @@
+use crate::model::common::Position;
 use serde::{Deserialize, Serialize};
 
+/// A violation a rule must report on the code of a test. The end of the violation
+/// and its message (a regular expression) are only checked when specified.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExpectedViolation {
+    pub start: Position,
+    pub end: Option<Position>,
+    pub message: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RuleTest {
     pub annotation_count: u32,
     pub filename: String,
     #[serde(rename = "code")]
     pub code_base64: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected_violations: Vec<ExpectedViolation>,
+    // code of the test once the fixes of the violations are applied (snapshot)
+    #[serde(
+        rename = "fixed_code",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fixed_code_base64: Option<String>,
 }
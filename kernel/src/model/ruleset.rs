@@ -1,9 +1,31 @@
 use crate::model::rule::Rule;
+use crate::model::signature::Signature;
 use serde::{Deserialize, Serialize};
+use sha2::Digest;
 
 #[derive(Clone, Deserialize, Debug, Serialize)]
 pub struct RuleSet {
     pub name: String,
     pub description: Option<String>,
     pub rules: Vec<Rule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl RuleSet {
+    /// Checksum of the rules of the ruleset: the hash of the names and content checksums
+    /// (see `Rule::compute_content_checksum`) of the rules, sorted by name.
+    pub fn compute_checksum(&self) -> String {
+        let mut rules: Vec<(&str, String)> = self
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.compute_content_checksum()))
+            .collect();
+        rules.sort();
+        let mut hasher = sha2::Sha256::new();
+        for (name, checksum) in rules {
+            hasher.update(format!("{}:{}\n", name, checksum).as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
 }
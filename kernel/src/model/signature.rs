@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+/// Detached Ed25519 signature of a ruleset or a rule. Both values are base64-encoded.
+#[derive(Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
+pub struct Signature {
+    pub public_key: String,
+    pub signature: String,
+}
@@ -3,6 +3,7 @@ use crate::model::rule::{RuleCategory, RuleSeverity};
 
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
+use sha2::Digest;
 
 #[derive(Copy, Clone, Deserialize, Debug, Serialize, Eq, PartialEq)]
 pub enum EditType {
@@ -37,4 +38,118 @@ pub struct Violation {
     pub severity: RuleSeverity,
     pub category: RuleCategory,
     pub fixes: Vec<Fix>,
+    // see `Violation::compute_fingerprint`, set when the file is analyzed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    pub fingerprint: Option<String>,
+}
+
+// Normalize a line of code for the fingerprints: consecutive whitespaces are replaced by
+// one space and leading and trailing whitespaces are removed.
+fn normalize_line(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+impl Violation {
+    /// Compute the fingerprint of the violation: the SHA256 of the rule, the path of the
+    /// file (with `/` separators) and the code of the lines of the violation with the
+    /// closest non-empty line before and after them. Whitespaces are normalized and the
+    /// fingerprint does not depend on the position of the violation so that it does not
+    /// change when lines are added or removed elsewhere in the file.
+    pub fn compute_fingerprint(
+        &self,
+        rule_name: &str,
+        filename: &str,
+        file_content: &str,
+    ) -> String {
+        let lines: Vec<String> = file_content.lines().map(normalize_line).collect();
+        let start = (self.start.line as usize)
+            .saturating_sub(1)
+            .min(lines.len());
+        let end = (self.end.line as usize).clamp(start, lines.len());
+        let before = lines[..start].iter().rev().find(|l| !l.is_empty());
+        let after = lines[end..].iter().find(|l| !l.is_empty());
+        let path = filename.replace('\\', "/");
+        let path = path.trim_start_matches("./");
+
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(
+            format!(
+                "{}\n{}\n{}\n{}\n{}",
+                rule_name,
+                path,
+                before.map(String::as_str).unwrap_or_default(),
+                lines[start..end].join("\n"),
+                after.map(String::as_str).unwrap_or_default()
+            )
+            .as_bytes(),
+        );
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::common::PositionBuilder;
+
+    #[test]
+    fn test_compute_fingerprint() {
+        let violation = |start: u32, end: u32| {
+            ViolationBuilder::default()
+                .start(
+                    PositionBuilder::default()
+                        .line(start)
+                        .col(1)
+                        .build()
+                        .unwrap(),
+                )
+                .end(PositionBuilder::default().line(end).col(1).build().unwrap())
+                .message("violation".to_string())
+                .severity(RuleSeverity::Error)
+                .category(RuleCategory::BestPractices)
+                .fixes(vec![])
+                .build()
+                .unwrap()
+        };
+        let content = "import os\nr = os.system(cmd)\nprint(1)\n";
+        let fingerprint = violation(2, 2).compute_fingerprint("rule", "src/file.py", content);
+
+        // empty lines, lines added before the context or whitespaces do not change it
+        let shifted_content = "# comment\nimport os\n\n    r  =  os.system(cmd)\nprint(1)\n";
+        assert_eq!(
+            fingerprint,
+            violation(4, 4).compute_fingerprint("rule", "src/file.py", shifted_content)
+        );
+        assert_eq!(
+            fingerprint,
+            violation(2, 2).compute_fingerprint("rule", "./src\\file.py", content)
+        );
+        // the code, the rule and the file change it
+        assert_ne!(
+            fingerprint,
+            violation(3, 3).compute_fingerprint("rule", "src/file.py", content)
+        );
+        assert_ne!(
+            fingerprint,
+            violation(2, 3).compute_fingerprint("rule", "src/file.py", content)
+        );
+        assert_ne!(
+            fingerprint,
+            violation(2, 2).compute_fingerprint("rule2", "src/file.py", content)
+        );
+        assert_ne!(
+            fingerprint,
+            violation(2, 2).compute_fingerprint("rule", "src/file2.py", content)
+        );
+        // the context changes it
+        assert_ne!(
+            fingerprint,
+            violation(2, 2).compute_fingerprint(
+                "rule",
+                "src/file.py",
+                "import sys\nr = os.system(cmd)\nprint(1)\n"
+            )
+        );
+    }
 }
@@ -0,0 +1,347 @@
+use crate::model::rule::Rule;
+use crate::model::ruleset::RuleSet;
+use crate::model::signature::Signature;
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose;
+use base64::Engine;
+use ed25519_dalek::{Signer, Verifier};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+// Write a JSON value with the keys of the objects sorted so that the serialization
+// of a value is always the same, whatever the order of its fields.
+fn write_canonical_json(value: &Value, output: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            output.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                output.push_str(Value::String(key.clone()).to_string().as_str());
+                output.push(':');
+                write_canonical_json(&map[key], output);
+            }
+            output.push('}');
+        }
+        Value::Array(values) => {
+            output.push('[');
+            for (index, v) in values.iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_canonical_json(v, output);
+            }
+            output.push(']');
+        }
+        _ => output.push_str(value.to_string().as_str()),
+    }
+}
+
+fn get_canonical_json(value: &Value) -> Vec<u8> {
+    let mut output = String::new();
+    write_canonical_json(value, &mut output);
+    output.into_bytes()
+}
+
+/// Content signed for a ruleset: its canonical JSON serialization, without the signature
+/// of the ruleset.
+pub fn get_ruleset_signed_content(ruleset: &RuleSet) -> Result<Vec<u8>> {
+    let mut value = serde_json::to_value(ruleset)?;
+    if let Some(object) = value.as_object_mut() {
+        object.remove("signature");
+    }
+    Ok(get_canonical_json(&value))
+}
+
+/// Content signed for a rule: the canonical JSON serialization of what is executed by
+/// the rule and of what it reports (severity, category). It only uses fields also sent
+/// to the server so that it can verify rules.
+pub fn get_rule_signed_content(rule: &Rule) -> Vec<u8> {
+    get_canonical_json(&json!({
+        "name": rule.name,
+        "language": rule.language,
+        "code": rule.code_base64,
+        "tree_sitter_query": rule.tree_sitter_query_base64,
+        "severity": rule.severity,
+        "category": rule.category,
+        "variables": rule.variables,
+    }))
+}
+
+fn decode_key_bytes<const N: usize>(content: &str) -> Result<[u8; N]> {
+    let bytes = general_purpose::STANDARD
+        .decode(content.trim())
+        .context("key is not base64")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("key must be {} bytes long", N))
+}
+
+/// Parse a private key: the base64 of the 32 bytes of an Ed25519 secret key.
+pub fn parse_signing_key(content: &str) -> Result<SigningKey> {
+    Ok(SigningKey::from_bytes(&decode_key_bytes(content)?))
+}
+
+/// Parse a public key: the base64 of the 32 bytes of an Ed25519 public key.
+pub fn parse_verifying_key(content: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&decode_key_bytes(content)?).context("invalid public key")
+}
+
+/// Generate a new private key.
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::generate(&mut rand_core::OsRng)
+}
+
+/// Encode a key with the format read by `parse_signing_key` and `parse_verifying_key`.
+pub fn encode_key(key_bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(key_bytes)
+}
+
+/// Write a new private key in a file only readable by its owner. Fails if the file
+/// already exists so that a key is never overwritten.
+pub fn write_signing_key(path: &Path, key: &SigningKey) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("cannot create key file {}", path.display()))?;
+    file.write_all(encode_key(key.as_bytes()).as_bytes())
+        .with_context(|| format!("cannot write key file {}", path.display()))
+}
+
+/// Read a private key from a file.
+pub fn read_signing_key(path: &Path) -> Result<SigningKey> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("cannot read key file {}", path.display()))?;
+    parse_signing_key(content.as_str())
+        .with_context(|| format!("invalid key file {}", path.display()))
+}
+
+/// Read the trusted public keys from a file: one key per line, lines starting with `#`
+/// are comments.
+pub fn read_trusted_keys(path: &Path) -> Result<Vec<VerifyingKey>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("cannot read trusted keys file {}", path.display()))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            parse_verifying_key(l)
+                .with_context(|| format!("invalid trusted key {} in {}", l, path.display()))
+        })
+        .collect()
+}
+
+/// Sign some content (e.g. the result of `get_rule_signed_content`).
+pub fn sign_content(content: &[u8], key: &SigningKey) -> Signature {
+    Signature {
+        public_key: encode_key(key.verifying_key().as_bytes()),
+        signature: general_purpose::STANDARD.encode(key.sign(content).to_bytes()),
+    }
+}
+
+// Check a signature: it must exist, use a trusted key and match the content.
+// The description of the signed object (e.g. "ruleset foo") is used in the errors.
+fn verify(
+    content: &[u8],
+    signature: Option<&Signature>,
+    trusted_keys: &[VerifyingKey],
+    description: &str,
+) -> Result<()> {
+    let signature = signature.ok_or_else(|| anyhow!("{} is not signed", description))?;
+    let public_key = parse_verifying_key(signature.public_key.as_str())
+        .with_context(|| format!("invalid public key for {}", description))?;
+    if !trusted_keys.contains(&public_key) {
+        return Err(anyhow!(
+            "{} is signed with an untrusted key {}",
+            description,
+            signature.public_key
+        ));
+    }
+    let signature_bytes: [u8; 64] = general_purpose::STANDARD
+        .decode(signature.signature.as_str())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| anyhow!("invalid signature for {}", description))?;
+    public_key
+        .verify(
+            content,
+            &ed25519_dalek::Signature::from_bytes(&signature_bytes),
+        )
+        .map_err(|_| anyhow!("invalid signature for {}", description))
+}
+
+/// Sign a ruleset: each rule is signed, then the ruleset (including the signatures of
+/// its rules).
+pub fn sign_ruleset(ruleset: &mut RuleSet, key: &SigningKey) -> Result<()> {
+    for rule in ruleset.rules.iter_mut() {
+        rule.signature = Some(sign_content(get_rule_signed_content(rule).as_slice(), key));
+    }
+    ruleset.signature = Some(sign_content(
+        get_ruleset_signed_content(ruleset)?.as_slice(),
+        key,
+    ));
+    Ok(())
+}
+
+/// Verify the signature of a ruleset with the trusted public keys.
+pub fn verify_ruleset(ruleset: &RuleSet, trusted_keys: &[VerifyingKey]) -> Result<()> {
+    verify(
+        get_ruleset_signed_content(ruleset)?.as_slice(),
+        ruleset.signature.as_ref(),
+        trusted_keys,
+        format!("ruleset {}", ruleset.name).as_str(),
+    )
+}
+
+/// Verify the signature of a rule (see `get_rule_signed_content`) with the trusted
+/// public keys.
+pub fn verify_rule_signature(rule: &Rule, trusted_keys: &[VerifyingKey]) -> Result<()> {
+    verify(
+        get_rule_signed_content(rule).as_slice(),
+        rule.signature.as_ref(),
+        trusted_keys,
+        format!("rule {}", rule.name).as_str(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::common::Language;
+    use crate::model::rule::{RuleCategory, RuleSeverity, RuleType};
+    use std::collections::HashMap;
+
+    fn ruleset() -> RuleSet {
+        RuleSet {
+            name: "myruleset".to_string(),
+            description: None,
+            rules: vec![Rule {
+                name: "myruleset/myrule".to_string(),
+                short_description_base64: None,
+                description_base64: None,
+                category: RuleCategory::BestPractices,
+                severity: RuleSeverity::Warning,
+                language: Language::Python,
+                rule_type: RuleType::TreeSitterQuery,
+                entity_checked: None,
+                code_base64: "Y29kZQ==".to_string(),
+                checksum: "".to_string(),
+                pattern: None,
+                tree_sitter_query_base64: Some("cXVlcnk=".to_string()),
+                variables: HashMap::from([
+                    ("a".to_string(), "1".to_string()),
+                    ("b".to_string(), "2".to_string()),
+                ]),
+                tests: vec![],
+                signature: None,
+            }],
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        let value = json!({"b": [1, {"d": null, "c": "x\""}], "a": true});
+        assert_eq!(
+            r#"{"a":true,"b":[1,{"c":"x\"","d":null}]}"#,
+            String::from_utf8(get_canonical_json(&value)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify_ruleset() {
+        let key = generate_signing_key();
+        let trusted_keys =
+            vec![parse_verifying_key(encode_key(key.verifying_key().as_bytes()).as_str()).unwrap()];
+
+        let mut ruleset = ruleset();
+        assert_eq!(
+            "ruleset myruleset is not signed",
+            verify_ruleset(&ruleset, &trusted_keys)
+                .unwrap_err()
+                .to_string()
+        );
+
+        sign_ruleset(&mut ruleset, &key).unwrap();
+        assert!(verify_ruleset(&ruleset, &trusted_keys).is_ok());
+        assert!(verify_rule_signature(&ruleset.rules[0], &trusted_keys).is_ok());
+
+        // the signature survives a serialization round-trip
+        let serialized = serde_json::to_string(&ruleset).unwrap();
+        let deserialized: RuleSet = serde_json::from_str(serialized.as_str()).unwrap();
+        assert!(verify_ruleset(&deserialized, &trusted_keys).is_ok());
+
+        // untrusted key
+        let other_key = generate_signing_key().verifying_key();
+        assert!(verify_ruleset(&ruleset, &[other_key])
+            .unwrap_err()
+            .to_string()
+            .starts_with("ruleset myruleset is signed with an untrusted key"));
+
+        // tampered ruleset
+        ruleset.rules[0].code_base64 = "Y29kZTI=".to_string();
+        assert_eq!(
+            "invalid signature for ruleset myruleset",
+            verify_ruleset(&ruleset, &trusted_keys)
+                .unwrap_err()
+                .to_string()
+        );
+        assert_eq!(
+            "invalid signature for rule myruleset/myrule",
+            verify_rule_signature(&ruleset.rules[0], &trusted_keys)
+                .unwrap_err()
+                .to_string()
+        );
+
+        // what the rule reports is signed too
+        sign_ruleset(&mut ruleset, &key).unwrap();
+        let mut changed = vec![ruleset.rules[0].clone(); 3];
+        changed[0].severity = RuleSeverity::Notice;
+        changed[1].category = RuleCategory::Security;
+        changed[2]
+            .variables
+            .insert("a".to_string(), "3".to_string());
+        for rule in changed {
+            assert!(verify_rule_signature(&rule, &trusted_keys).is_err());
+        }
+    }
+
+    #[test]
+    fn test_write_signing_key() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("signing.key");
+        let key = generate_signing_key();
+        write_signing_key(&path, &key).unwrap();
+        assert_eq!(key.as_bytes(), read_signing_key(&path).unwrap().as_bytes());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        // an existing key is never overwritten
+        assert!(write_signing_key(&path, &generate_signing_key()).is_err());
+        assert_eq!(key.as_bytes(), read_signing_key(&path).unwrap().as_bytes());
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let key = generate_signing_key();
+        let parsed = parse_signing_key(encode_key(key.as_bytes()).as_str()).unwrap();
+        assert_eq!(key.verifying_key(), parsed.verifying_key());
+        assert!(parse_verifying_key("Zm9v").is_err());
+        assert!(parse_signing_key("not base64!").is_err());
+    }
+}
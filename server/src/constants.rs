@@ -7,3 +7,5 @@ pub const ERROR_CODE_LANGUAGE_MISMATCH: &str = "language-mismatch";
 // no root node when trying to get the AST
 pub const ERROR_CODE_NO_ROOT_NODE: &str = "no-root-node";
 pub const ERROR_CHECKSUM_MISMATCH: &str = "checksum-mismatch";
+// when a rule is not signed or its signature is not valid
+pub const ERROR_INVALID_SIGNATURE: &str = "invalid-signature";
@@ -1,5 +1,6 @@
 use kernel::model::common::Language;
 use kernel::model::rule::{EntityChecked, RuleCategory, RuleSeverity, RuleType};
+use kernel::model::signature::Signature;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -27,6 +28,8 @@ pub struct ServerRule {
     #[serde(rename = "tree_sitter_query")]
     pub tree_sitter_query_base64: Option<String>,
     pub variables: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
 }
 
 #[derive(Clone, Deserialize, Debug, Serialize)]
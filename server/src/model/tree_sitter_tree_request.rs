@@ -7,4 +7,6 @@ pub struct TreeSitterRequest {
     pub file_encoding: String,
     #[serde(rename = "code")]
     pub code_base64: String,
+    // used to select the grammar when a language has several (e.g. .ts vs .tsx)
+    pub filename: Option<String>,
 }
@@ -1,6 +1,6 @@
 use crate::constants::{
     ERROR_CHECKSUM_MISMATCH, ERROR_CODE_LANGUAGE_MISMATCH, ERROR_CODE_NOT_BASE64,
-    ERROR_DECODING_BASE64,
+    ERROR_DECODING_BASE64, ERROR_INVALID_SIGNATURE,
 };
 use crate::model::analysis_request::{AnalysisRequest, ServerRule};
 use crate::model::analysis_response::{AnalysisResponse, RuleResponse};
@@ -8,9 +8,31 @@ use crate::model::violation::violation_to_server;
 use kernel::analysis::analyze::analyze;
 use kernel::model::analysis::AnalysisOptions;
 use kernel::model::rule::{Rule, RuleCategory, RuleInternal, RuleSeverity};
+use kernel::signature_utils::{verify_rule_signature, VerifyingKey};
 use kernel::utils::decode_base64_string;
 use std::collections::HashMap;
 
+// convert a rule of a request into a rule, with the default values of the optional fields
+fn server_rule_to_rule(r: &ServerRule) -> Rule {
+    Rule {
+        name: r.name.clone(),
+        short_description_base64: r.short_description_base64.clone(),
+        description_base64: r.description_base64.clone(),
+        category: r.category.unwrap_or(RuleCategory::BestPractices),
+        severity: r.severity.unwrap_or(RuleSeverity::Warning),
+        language: r.language,
+        rule_type: r.rule_type,
+        entity_checked: r.entity_checked,
+        code_base64: r.code_base64.clone(),
+        checksum: r.checksum.clone().unwrap_or("".to_string()),
+        pattern: r.pattern.clone(),
+        tree_sitter_query_base64: r.tree_sitter_query_base64.clone(),
+        variables: r.variables.clone().unwrap_or(HashMap::new()),
+        tests: vec![],
+        signature: r.signature.clone(),
+    }
+}
+
 pub fn process_analysis_request(request: AnalysisRequest) -> AnalysisResponse {
     let rules_with_invalid_language: Vec<ServerRule> = request
         .rules
@@ -25,26 +47,7 @@ pub fn process_analysis_request(request: AnalysisRequest) -> AnalysisResponse {
         };
     }
 
-    let server_rules_to_rules: Vec<Rule> = request
-        .rules
-        .iter()
-        .map(|r| Rule {
-            name: r.name.clone(),
-            short_description_base64: r.short_description_base64.clone(),
-            description_base64: r.description_base64.clone(),
-            category: r.category.unwrap_or(RuleCategory::BestPractices),
-            severity: r.severity.unwrap_or(RuleSeverity::Warning),
-            language: r.language,
-            rule_type: r.rule_type,
-            entity_checked: r.entity_checked,
-            code_base64: r.code_base64.clone(),
-            checksum: r.checksum.clone().unwrap_or("".to_string()),
-            pattern: r.pattern.clone(),
-            tree_sitter_query_base64: r.tree_sitter_query_base64.clone(),
-            variables: r.variables.clone().unwrap_or(HashMap::new()),
-            tests: vec![],
-        })
-        .collect();
+    let server_rules_to_rules: Vec<Rule> = request.rules.iter().map(server_rule_to_rule).collect();
 
     // Convert the rules from the server into internal rules
     let rules_converted: Result<Vec<RuleInternal>, anyhow::Error> = server_rules_to_rules
@@ -114,6 +117,23 @@ pub fn process_analysis_request(request: AnalysisRequest) -> AnalysisResponse {
     }
 }
 
+/// Process a request only if all its rules are signed with one of the trusted keys.
+pub fn process_signed_analysis_request(
+    request: AnalysisRequest,
+    trusted_keys: &[VerifyingKey],
+) -> AnalysisResponse {
+    for rule in request.rules.iter().map(server_rule_to_rule) {
+        if let Err(e) = verify_rule_signature(&rule, trusted_keys) {
+            eprintln!("Rule {} rejected: {}", rule.name, e);
+            return AnalysisResponse {
+                rule_responses: vec![],
+                errors: vec![ERROR_INVALID_SIGNATURE.to_string()],
+            };
+        }
+    }
+    process_analysis_request(request)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::model::analysis_request::ServerRule;
@@ -121,6 +141,7 @@ mod tests {
         common::Language,
         rule::{RuleCategory, RuleSeverity, RuleType},
     };
+    use kernel::signature_utils::{generate_signing_key, get_rule_signed_content, sign_content};
 
     use super::*;
 
@@ -147,6 +168,7 @@ mod tests {
                     pattern: None,
                     tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
                     variables: None,
+                    signature: None,
                 }
             ]
         };
@@ -179,6 +201,7 @@ mod tests {
                     pattern: None,
                     tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
                     variables: None,
+                    signature: None,
                 }
             ]
         };
@@ -213,6 +236,7 @@ mod tests {
                     pattern: None,
                     tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
                     variables: None,
+                    signature: None,
                 }
             ]
         };
@@ -247,6 +271,7 @@ mod tests {
                     pattern: None,
                     tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
                     variables: None,
+                    signature: None,
                 }
             ]
         };
@@ -281,6 +306,7 @@ mod tests {
                     pattern: None,
                     tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
                     variables: None,
+                    signature: None,
                 }
             ]
         };
@@ -291,4 +317,67 @@ mod tests {
             response.errors.get(0).unwrap()
         );
     }
+
+    #[test]
+    fn test_request_signature() {
+        let mut request = AnalysisRequest {
+            filename: "myfile.py".to_string(),
+            language: Language::Python,
+            file_encoding: "utf-8".to_string(),
+            code_base64: "ZGVmIGZvbyhhcmcxKToKICAgIHBhc3M=".to_string(),
+            options: None,
+            rules: vec![
+                ServerRule{
+                    name: "myrule".to_string(),
+                    short_description_base64: None,
+                    description_base64: None,
+                    category: Some(RuleCategory::BestPractices),
+                    severity: Some(RuleSeverity::Warning),
+                    language: Language::Python,
+                    rule_type: RuleType::TreeSitterQuery,
+                    entity_checked: None,
+                    code_base64: "ZnVuY3Rpb24gdmlzaXQobm9kZSwgZmlsZW5hbWUsIGNvZGUpIHsKICAgIGNvbnN0IGZ1bmN0aW9uTmFtZSA9IG5vZGUuY2FwdHVyZXNbIm5hbWUiXTsKICAgIGlmKGZ1bmN0aW9uTmFtZSkgewogICAgICAgIGNvbnN0IGVycm9yID0gYnVpbGRFcnJvcihmdW5jdGlvbk5hbWUuc3RhcnQubGluZSwgZnVuY3Rpb25OYW1lLnN0YXJ0LmNvbCwgZnVuY3Rpb25OYW1lLmVuZC5saW5lLCBmdW5jdGlvbk5hbWUuZW5kLmNvbCwKICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgImludmFsaWQgbmFtZSIsICJDUklUSUNBTCIsICJzZWN1cml0eSIpOwoKICAgICAgICBjb25zdCBlZGl0ID0gYnVpbGRFZGl0KGZ1bmN0aW9uTmFtZS5zdGFydC5saW5lLCBmdW5jdGlvbk5hbWUuc3RhcnQuY29sLCBmdW5jdGlvbk5hbWUuZW5kLmxpbmUsIGZ1bmN0aW9uTmFtZS5lbmQuY29sLCAidXBkYXRlIiwgImJhciIpOwogICAgICAgIGNvbnN0IGZpeCA9IGJ1aWxkRml4KCJ1c2UgYmFyIiwgW2VkaXRdKTsKICAgICAgICBhZGRFcnJvcihlcnJvci5hZGRGaXgoZml4KSk7CiAgICB9Cn0=".to_string(),
+                    checksum: Some("f546e49732dc071fd5da82e1a2d9bcf5cf9a824c3679d8b59237c4ba23340057".to_string()),
+                    pattern: None,
+                    tree_sitter_query_base64: Some("KGZ1bmN0aW9uX2RlZmluaXRpb24KICAgIG5hbWU6IChpZGVudGlmaWVyKSBAbmFtZQogIHBhcmFtZXRlcnM6IChwYXJhbWV0ZXJzKSBAcGFyYW1zCik=".to_string()),
+                    variables: None,
+                    signature: None,
+                }
+            ]
+        };
+        let key = generate_signing_key();
+        let trusted_keys = vec![key.verifying_key()];
+
+        // unsigned rule
+        let response = process_signed_analysis_request(request.clone(), &trusted_keys);
+        assert_eq!(0, response.rule_responses.len());
+        assert_eq!(
+            &ERROR_INVALID_SIGNATURE.to_string(),
+            response.errors.get(0).unwrap()
+        );
+
+        // signed rule
+        let content = get_rule_signed_content(&server_rule_to_rule(&request.rules[0]));
+        request.rules[0].signature = Some(sign_content(content.as_slice(), &key));
+        let response = process_signed_analysis_request(request.clone(), &trusted_keys);
+        assert!(response.errors.is_empty());
+        assert_eq!(1, response.rule_responses.len());
+
+        // the severity of the rule changed after it was signed
+        let mut changed_request = request.clone();
+        changed_request.rules[0].severity = Some(RuleSeverity::Error);
+        let response = process_signed_analysis_request(changed_request, &trusted_keys);
+        assert_eq!(
+            &ERROR_INVALID_SIGNATURE.to_string(),
+            response.errors.get(0).unwrap()
+        );
+
+        // signed with an untrusted key
+        let response =
+            process_signed_analysis_request(request, &[generate_signing_key().verifying_key()]);
+        assert_eq!(
+            &ERROR_INVALID_SIGNATURE.to_string(),
+            response.errors.get(0).unwrap()
+        );
+    }
 }
@@ -20,7 +20,11 @@ pub fn process_tree_sitter_tree_request(request: TreeSitterRequest) -> TreeSitte
         };
     }
 
-    let tree = get_tree(&decoded.unwrap(), &request.language);
+    let tree = get_tree(
+        &decoded.unwrap(),
+        &request.language,
+        request.filename.as_deref().unwrap_or_default(),
+    );
 
     if tree.is_none() {
         return no_root_node;
@@ -49,6 +53,7 @@ mod tests {
             code_base64: "ZnVuY3Rpb24gdmlzaXQobm9kZSwgZmlsZW5hbWUsIGNvZGUpIHsKICAgIGNvbnN0IGZ1bmN0aW9uTmFtZSA9IG5vZGUuY2FwdHVyZXNbIm5hbWUiXTsKICAgIGlmKGZ1bmN0aW9uTmFtZSkgewogICAgICAgIGNvbnN0IGVycm9yID0gYnVpbGRFcnJvcihmdW5jdGlvbk5hbWUuc3RhcnQubGluZSwgZnVuY3Rpb25OYW1lLnN0YXJ0LmNvbCwgZnVuY3Rpb25OYW1lLmVuZC5saW5lLCBmdW5jdGlvbk5hbWUuZW5kLmNvbCwKICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgImludmFsaWQgbmFtZSIsICJDUklUSUNBTCIsICJzZWN1cml0eSIpOwoKICAgICAgICBjb25zdCBlZGl0ID0gYnVpbGRFZGl0KGZ1bmN0aW9uTmFtZS5zdGFydC5saW5lLCBmdW5jdGlvbk5hbWUuc3RhcnQuY29sLCBmdW5jdGlvbk5hbWUuZW5kLmxpbmUsIGZ1bmN0aW9uTmFtZS5lbmQuY29sLCAidXBkYXRlIiwgImJhciIpOwogICAgICAgIGNvbnN0IGZpeCA9IGJ1aWxkRml4KCJ1c2UgYmFyIiwgW2VkaXRdKTsKICAgICAgICBhZGRFcnJvcihlcnJvci5hZGRGaXgoZml4KSk7CiAgICB9Cn0=".to_string(),
             file_encoding: "utf-8".to_string(),
             language: Language::Python,
+            filename: None,
         };
         let response = process_tree_sitter_tree_request(request);
         assert!(response.errors.is_empty());
@@ -62,6 +67,7 @@ mod tests {
             code_base64: "we2323423423090909)()(&(*&!@!@=".to_string(),
             file_encoding: "utf-8".to_string(),
             language: Language::Python,
+            filename: None,
         };
         let response = process_tree_sitter_tree_request(request);
         assert_eq!(